@@ -0,0 +1,279 @@
+mod envelope;
+mod length_counter;
+pub(crate) mod pulse;
+mod triangle;
+mod noise;
+mod dmc;
+mod frame_counter;
+
+use pulse::Pulse;
+use triangle::Triangle;
+use noise::Noise;
+use dmc::Dmc;
+use frame_counter::{FrameCounter, FrameClock};
+
+pub const CPU_FREQUENCY: f64 = 1_789_773.0;
+
+// samples are produced once per cpu cycle, that is about one second of sound
+const SAMPLE_BUFFER_SIZE: usize = 1_789_773;
+
+// channel outputs are recorded for the debug view every 233 cpu cycles, 128 points per frame
+const WAVEFORM_PERIOD: u32 = 233;
+pub const WAVEFORM_SIZE: usize = 128;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Channel {
+    Pulse1,
+    Pulse2,
+    Triangle,
+    Noise,
+    Dmc,
+}
+
+impl Channel {
+    pub const ALL: [Channel; 5] = [Channel::Pulse1, Channel::Pulse2, Channel::Triangle, Channel::Noise, Channel::Dmc];
+
+    // highest value the channel can output
+    pub fn max_output(&self) -> u8 {
+        match self {
+            Channel::Dmc => 127,
+            _ => 15,
+        }
+    }
+}
+
+pub struct Apu {
+    pulse_1: Pulse,
+    pulse_2: Pulse,
+    triangle: Triangle,
+    noise: Noise,
+    dmc: Dmc,
+    frame_counter: FrameCounter,
+
+    muted: [bool; 5],
+    solo: Option<Channel>,
+    volume: [f32; 5],
+
+    waveforms: [Vec<u8>; 5],
+    waveform_counter: u32,
+
+    // sound channels of the cartridge, already mixed by the mapper
+    expansion_output: f32,
+
+    odd_cycle: bool,
+    samples: Vec<f32>,
+}
+
+impl Apu {
+    pub fn new() -> Apu {
+        Apu {
+            pulse_1: Pulse::new(true),
+            pulse_2: Pulse::new(false),
+            triangle: Triangle::new(),
+            noise: Noise::new(),
+            dmc: Dmc::new(),
+            frame_counter: FrameCounter::new(),
+
+            muted: [false; 5],
+            solo: None,
+            volume: [1.0; 5],
+
+            waveforms: Default::default(),
+            waveform_counter: 0,
+
+            expansion_output: 0.0,
+
+            odd_cycle: false,
+            samples: Vec::new(),
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.write_register(0x4015, 0x00);
+        self.frame_counter.reset();
+    }
+
+    pub fn write_register(&mut self, address: u16, data: u8) {
+        match address {
+            0x4000..=0x4003 => self.pulse_1.write_register(address & 0x03, data),
+            0x4004..=0x4007 => self.pulse_2.write_register(address & 0x03, data),
+            0x4008..=0x400B => self.triangle.write_register(address & 0x03, data),
+            0x400C..=0x400F => self.noise.write_register(address & 0x03, data),
+            0x4010..=0x4013 => self.dmc.write_register(address & 0x03, data),
+            0x4015 => {
+                self.pulse_1.set_enabled(data & 0x01 != 0);
+                self.pulse_2.set_enabled(data & 0x02 != 0);
+                self.triangle.set_enabled(data & 0x04 != 0);
+                self.noise.set_enabled(data & 0x08 != 0);
+                self.dmc.set_enabled(data & 0x10 != 0);
+            },
+            0x4017 => self.frame_counter.write(data, self.odd_cycle),
+            _ => (),
+        }
+    }
+
+    pub fn read_status(&mut self) -> u8 {
+        let data = self.peek_status();
+        self.frame_counter.clear_irq_flag();
+        data
+    }
+
+    pub fn peek_status(&self) -> u8 {
+        let mut data = 0;
+        if self.pulse_1.active()  { data |= 0x01 }
+        if self.pulse_2.active()  { data |= 0x02 }
+        if self.triangle.active() { data |= 0x04 }
+        if self.noise.active()    { data |= 0x08 }
+        if self.dmc.active()      { data |= 0x10 }
+        if self.frame_counter.irq_flag() { data |= 0x40 }
+        if self.dmc.irq_flag()    { data |= 0x80 }
+        data
+    }
+
+    // level of the apu irq line, it stays asserted until the flags are acknowledged
+    pub fn irq(&self) -> bool {
+        self.frame_counter.irq_flag() || self.dmc.irq_flag()
+    }
+
+    pub fn dmc_sample_request(&self) -> Option<u16> {
+        self.dmc.sample_request()
+    }
+
+    pub fn load_dmc_sample(&mut self, data: u8) {
+        self.dmc.load_sample(data);
+    }
+
+    pub fn clock(&mut self) { // every cpu cycle
+        if self.odd_cycle {
+            self.pulse_1.clock_timer();
+            self.pulse_2.clock_timer();
+        }
+        self.odd_cycle = !self.odd_cycle;
+        self.triangle.clock_timer();
+        self.noise.clock_timer();
+        self.dmc.clock_timer();
+
+        match self.frame_counter.clock() {
+            FrameClock::Quarter => self.clock_quarter_frame(),
+            FrameClock::Half => {
+                self.clock_quarter_frame();
+                self.clock_half_frame();
+            },
+            FrameClock::None => (),
+        }
+
+        if self.samples.len() < SAMPLE_BUFFER_SIZE {
+            let sample = self.output();
+            self.samples.push(sample);
+        }
+
+        self.waveform_counter += 1;
+        if self.waveform_counter == WAVEFORM_PERIOD {
+            self.waveform_counter = 0;
+            for channel in Channel::ALL.iter() {
+                let output = self.channel_output(*channel);
+                let waveform = &mut self.waveforms[*channel as usize];
+                if waveform.len() < WAVEFORM_SIZE {
+                    waveform.push(output);
+                }
+            }
+        }
+    }
+
+    pub fn set_expansion_output(&mut self, output: f32) {
+        self.expansion_output = output;
+    }
+
+    fn clock_quarter_frame(&mut self) {
+        self.pulse_1.clock_quarter_frame();
+        self.pulse_2.clock_quarter_frame();
+        self.triangle.clock_quarter_frame();
+        self.noise.clock_quarter_frame();
+    }
+
+    fn clock_half_frame(&mut self) {
+        self.pulse_1.clock_half_frame();
+        self.pulse_2.clock_half_frame();
+        self.triangle.clock_half_frame();
+        self.noise.clock_half_frame();
+    }
+
+    fn channel_output(&self, channel: Channel) -> u8 {
+        match channel {
+            Channel::Pulse1 => self.pulse_1.output(),
+            Channel::Pulse2 => self.pulse_2.output(),
+            Channel::Triangle => self.triangle.output(),
+            Channel::Noise => self.noise.output(),
+            Channel::Dmc => self.dmc.output(),
+        }
+    }
+
+    fn channel_gain(&self, channel: Channel) -> f32 {
+        let silenced = self.muted[channel as usize] || self.solo.map_or(false, |solo| solo != channel);
+        match silenced {
+            true  => 0.0,
+            false => self.volume[channel as usize],
+        }
+    }
+
+    fn scaled_output(&self, channel: Channel) -> f32 {
+        self.channel_output(channel) as f32 * self.channel_gain(channel)
+    }
+
+    // non-linear mixer, result is in range 0.0 - 1.0 (plus the expansion sound)
+    fn output(&self) -> f32 {
+        let pulse = self.scaled_output(Channel::Pulse1) + self.scaled_output(Channel::Pulse2);
+        let pulse_out = match pulse > 0.0 {
+            true  => 95.88 / (8128.0 / pulse + 100.0),
+            false => 0.0,
+        };
+        let tnd = self.scaled_output(Channel::Triangle) / 8227.0
+            + self.scaled_output(Channel::Noise) / 12241.0
+            + self.scaled_output(Channel::Dmc) / 22638.0;
+        let tnd_out = match tnd > 0.0 {
+            true  => 159.79 / (1.0 / tnd + 100.0),
+            false => 0.0,
+        };
+        pulse_out + tnd_out + self.expansion_output
+    }
+
+    pub fn set_muted(&mut self, channel: Channel, muted: bool) {
+        self.muted[channel as usize] = muted;
+    }
+
+    pub fn is_muted(&self, channel: Channel) -> bool {
+        self.muted[channel as usize]
+    }
+
+    // only the solo channel is heard, None turns solo mode off
+    pub fn set_solo(&mut self, channel: Option<Channel>) {
+        self.solo = channel;
+    }
+
+    pub fn solo(&self) -> Option<Channel> {
+        self.solo
+    }
+
+    pub fn set_volume(&mut self, channel: Channel, volume: f32) {
+        self.volume[channel as usize] = volume.max(0.0);
+    }
+
+    pub fn volume(&self, channel: Channel) -> f32 {
+        self.volume[channel as usize]
+    }
+
+    // raw channel output recorded since the last clear_waveforms, up to WAVEFORM_SIZE points
+    pub fn waveform(&self, channel: Channel) -> &[u8] {
+        &self.waveforms[channel as usize]
+    }
+
+    pub fn clear_waveforms(&mut self) {
+        self.waveforms.iter_mut().for_each(|waveform| waveform.clear());
+        self.waveform_counter = 0;
+    }
+
+    // takes all samples produced since the previous call, one sample per cpu cycle
+    pub fn pull_samples(&mut self) -> Vec<f32> {
+        std::mem::take(&mut self.samples)
+    }
+}
@@ -0,0 +1,146 @@
+// periods in cpu cycles (NTSC)
+const RATE_TABLE: [u16; 16] = [
+    428, 380, 340, 320, 286, 254, 226, 214, 190, 160, 142, 128, 106, 84, 72, 54,
+];
+
+pub struct Dmc {
+    irq_enabled: bool,
+    irq_flag: bool,
+    looped: bool,
+    timer_period: u16,
+    timer_counter: u16,
+
+    // memory reader
+    sample_address: u16,
+    sample_length: u16,
+    current_address: u16,
+    bytes_remaining: u16,
+    sample_buffer: Option<u8>,
+
+    // output unit
+    shift_register: u8,
+    bits_remaining: u8,
+    silence: bool,
+    output_level: u8,
+}
+
+impl Dmc {
+    pub fn new() -> Dmc {
+        Dmc {
+            irq_enabled: false,
+            irq_flag: false,
+            looped: false,
+            timer_period: RATE_TABLE[0],
+            timer_counter: 0,
+
+            sample_address: 0xC000,
+            sample_length: 1,
+            current_address: 0xC000,
+            bytes_remaining: 0,
+            sample_buffer: None,
+
+            shift_register: 0,
+            bits_remaining: 8,
+            silence: true,
+            output_level: 0,
+        }
+    }
+
+    pub fn write_register(&mut self, register: u16, data: u8) {
+        match register {
+            0 => { // IL-- RRRR
+                self.irq_enabled = data & 0x80 != 0;
+                if !self.irq_enabled {
+                    self.irq_flag = false;
+                }
+                self.looped = data & 0x40 != 0;
+                self.timer_period = RATE_TABLE[(data & 0x0F) as usize];
+            },
+            1 => self.output_level = data & 0x7F,
+            2 => self.sample_address = 0xC000 | ((data as u16) << 6),
+            3 => self.sample_length = ((data as u16) << 4) | 0x0001,
+            _ => (),
+        }
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.irq_flag = false;
+        if !enabled {
+            self.bytes_remaining = 0;
+        } else if self.bytes_remaining == 0 {
+            self.restart();
+        }
+    }
+
+    fn restart(&mut self) {
+        self.current_address = self.sample_address;
+        self.bytes_remaining = self.sample_length;
+    }
+
+    pub fn active(&self) -> bool {
+        self.bytes_remaining > 0
+    }
+
+    pub fn irq_flag(&self) -> bool {
+        self.irq_flag
+    }
+
+    // address of the next sample byte, when the memory reader wants one
+    pub fn sample_request(&self) -> Option<u16> {
+        match self.sample_buffer.is_none() && self.bytes_remaining > 0 {
+            true  => Some(self.current_address),
+            false => None,
+        }
+    }
+
+    pub fn load_sample(&mut self, data: u8) {
+        self.sample_buffer = Some(data);
+        self.current_address = match self.current_address {
+            0xFFFF => 0x8000,
+            address => address + 1,
+        };
+        self.bytes_remaining -= 1;
+        if self.bytes_remaining == 0 {
+            if self.looped {
+                self.restart();
+            } else if self.irq_enabled {
+                self.irq_flag = true;
+            }
+        }
+    }
+
+    pub fn clock_timer(&mut self) { // every cpu cycle
+        if self.timer_counter > 0 {
+            self.timer_counter -= 1;
+            return;
+        }
+        self.timer_counter = self.timer_period - 1;
+
+        if !self.silence {
+            if self.shift_register & 0x01 != 0 {
+                if self.output_level <= 125 {
+                    self.output_level += 2;
+                }
+            } else if self.output_level >= 2 {
+                self.output_level -= 2;
+            }
+        }
+        self.shift_register >>= 1;
+
+        self.bits_remaining -= 1;
+        if self.bits_remaining == 0 {
+            self.bits_remaining = 8;
+            match self.sample_buffer.take() {
+                Some(data) => {
+                    self.silence = false;
+                    self.shift_register = data;
+                },
+                None => self.silence = true,
+            }
+        }
+    }
+
+    pub fn output(&self) -> u8 {
+        self.output_level
+    }
+}
@@ -0,0 +1,56 @@
+pub struct Envelope {
+    start: bool,
+    looped: bool,
+    constant_volume: bool,
+    volume: u8,
+    divider: u8,
+    decay_level: u8,
+}
+
+impl Envelope {
+    pub fn new() -> Envelope {
+        Envelope {
+            start: false,
+            looped: false,
+            constant_volume: false,
+            volume: 0,
+            divider: 0,
+            decay_level: 0,
+        }
+    }
+
+    // --LC VVVV, L is shared with the length counter halt flag
+    pub fn set(&mut self, data: u8) {
+        self.looped = data & 0x20 != 0;
+        self.constant_volume = data & 0x10 != 0;
+        self.volume = data & 0x0F;
+    }
+
+    pub fn restart(&mut self) {
+        self.start = true;
+    }
+
+    pub fn clock(&mut self) { // quarter frame
+        if self.start {
+            self.start = false;
+            self.decay_level = 15;
+            self.divider = self.volume;
+        } else if self.divider == 0 {
+            self.divider = self.volume;
+            if self.decay_level > 0 {
+                self.decay_level -= 1;
+            } else if self.looped {
+                self.decay_level = 15;
+            }
+        } else {
+            self.divider -= 1;
+        }
+    }
+
+    pub fn output(&self) -> u8 {
+        match self.constant_volume {
+            true  => self.volume,
+            false => self.decay_level,
+        }
+    }
+}
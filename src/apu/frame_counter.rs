@@ -0,0 +1,118 @@
+// steps of the sequencer in cpu cycles after the reset (NTSC)
+const STEP_1: u32 = 7457;
+const STEP_2: u32 = 14913;
+const STEP_3: u32 = 22371;
+const STEP_4: u32 = 29829;
+const STEP_5: u32 = 37281;
+
+pub enum FrameClock {
+    None,
+    Quarter,
+    Half, // half frame is always clocked together with quarter frame
+}
+
+pub struct FrameCounter {
+    five_step_mode: bool,
+    irq_inhibit: bool,
+    irq_flag: bool,
+    cycle: u32,
+
+    // a write to $4017 resets the sequencer 3 or 4 cpu cycles later
+    pending_mode: Option<bool>,
+    reset_delay: u8,
+}
+
+impl FrameCounter {
+    pub fn new() -> FrameCounter {
+        FrameCounter {
+            five_step_mode: false,
+            irq_inhibit: false,
+            irq_flag: false,
+            cycle: 0,
+
+            pending_mode: None,
+            reset_delay: 0,
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.irq_flag = false;
+        self.cycle = 0;
+        self.pending_mode = None;
+        self.reset_delay = 0;
+    }
+
+    // MI-- ----
+    pub fn write(&mut self, data: u8, odd_cycle: bool) {
+        self.irq_inhibit = data & 0x40 != 0;
+        if self.irq_inhibit {
+            self.irq_flag = false;
+        }
+        self.pending_mode = Some(data & 0x80 != 0);
+        self.reset_delay = match odd_cycle {
+            true  => 4,
+            false => 3,
+        };
+    }
+
+    pub fn irq_flag(&self) -> bool {
+        self.irq_flag
+    }
+
+    pub fn clear_irq_flag(&mut self) {
+        self.irq_flag = false;
+    }
+
+    fn set_irq_flag(&mut self) {
+        if !self.irq_inhibit {
+            self.irq_flag = true;
+        }
+    }
+
+    pub fn clock(&mut self) -> FrameClock { // every cpu cycle
+        if self.reset_delay > 0 {
+            self.reset_delay -= 1;
+            if self.reset_delay == 0 {
+                if let Some(five_step_mode) = self.pending_mode.take() {
+                    self.five_step_mode = five_step_mode;
+                }
+                self.cycle = 0;
+                if self.five_step_mode {
+                    return FrameClock::Half;
+                }
+                return FrameClock::None;
+            }
+        }
+
+        self.cycle += 1;
+        match self.five_step_mode {
+            false => match self.cycle {
+                STEP_1 | STEP_3 => FrameClock::Quarter,
+                STEP_2 => FrameClock::Half,
+                c if c == STEP_4 - 1 => {
+                    self.set_irq_flag();
+                    FrameClock::None
+                },
+                STEP_4 => {
+                    self.set_irq_flag();
+                    FrameClock::Half
+                },
+                c if c == STEP_4 + 1 => {
+                    self.set_irq_flag();
+                    self.cycle = 0;
+                    FrameClock::None
+                },
+                _ => FrameClock::None,
+            },
+            true => match self.cycle {
+                STEP_1 | STEP_3 => FrameClock::Quarter,
+                STEP_2 | STEP_5 => FrameClock::Half,
+                c if c == STEP_5 + 1 => {
+                    self.cycle = 0;
+                    FrameClock::None
+                },
+                _ => FrameClock::None,
+            },
+        }
+    }
+}
@@ -0,0 +1,86 @@
+use super::envelope::Envelope;
+use super::length_counter::LengthCounter;
+
+// periods in cpu cycles (NTSC)
+const PERIOD_TABLE: [u16; 16] = [
+    4, 8, 16, 32, 64, 96, 128, 160, 202, 254, 380, 508, 762, 1016, 2034, 4068,
+];
+
+pub struct Noise {
+    shift_register: u16,
+    short_mode: bool,
+    timer_period: u16,
+    timer_counter: u16,
+
+    envelope: Envelope,
+    length_counter: LengthCounter,
+}
+
+impl Noise {
+    pub fn new() -> Noise {
+        Noise {
+            shift_register: 1,
+            short_mode: false,
+            timer_period: PERIOD_TABLE[0],
+            timer_counter: 0,
+
+            envelope: Envelope::new(),
+            length_counter: LengthCounter::new(),
+        }
+    }
+
+    pub fn write_register(&mut self, register: u16, data: u8) {
+        match register {
+            0 => { // --LC VVVV
+                self.length_counter.set_halt(data & 0x20 != 0);
+                self.envelope.set(data);
+            },
+            2 => { // M--- PPPP
+                self.short_mode = data & 0x80 != 0;
+                self.timer_period = PERIOD_TABLE[(data & 0x0F) as usize];
+            },
+            3 => { // LLLL L---
+                self.length_counter.load(data >> 3);
+                self.envelope.restart();
+            },
+            _ => (),
+        }
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.length_counter.set_enabled(enabled);
+    }
+
+    pub fn active(&self) -> bool {
+        self.length_counter.active()
+    }
+
+    pub fn clock_timer(&mut self) { // every cpu cycle
+        if self.timer_counter == 0 {
+            self.timer_counter = self.timer_period - 1;
+            let tap = match self.short_mode {
+                true  => 6,
+                false => 1,
+            };
+            let feedback = (self.shift_register ^ (self.shift_register >> tap)) & 0x01;
+            self.shift_register = (self.shift_register >> 1) | (feedback << 14);
+        } else {
+            self.timer_counter -= 1;
+        }
+    }
+
+    pub fn clock_quarter_frame(&mut self) {
+        self.envelope.clock();
+    }
+
+    pub fn clock_half_frame(&mut self) {
+        self.length_counter.clock();
+    }
+
+    pub fn output(&self) -> u8 {
+        if !self.length_counter.active() || self.shift_register & 0x01 != 0 {
+            return 0;
+        }
+        self.envelope.output()
+    }
+}
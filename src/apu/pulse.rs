@@ -0,0 +1,164 @@
+use super::envelope::Envelope;
+use super::length_counter::LengthCounter;
+
+const DUTY_TABLE: [[u8; 8]; 4] = [
+    [0, 0, 0, 0, 0, 0, 0, 1], // 12.5%
+    [0, 0, 0, 0, 0, 0, 1, 1], // 25%
+    [0, 0, 0, 0, 1, 1, 1, 1], // 50%
+    [1, 1, 1, 1, 1, 1, 0, 0], // 25% negated
+];
+
+struct Sweep {
+    enabled: bool,
+    period: u8,
+    negate: bool,
+    shift: u8,
+    reload: bool,
+    divider: u8,
+}
+
+impl Sweep {
+    fn new() -> Sweep {
+        Sweep {
+            enabled: false,
+            period: 0,
+            negate: false,
+            shift: 0,
+            reload: false,
+            divider: 0,
+        }
+    }
+
+    // EPPP NSSS
+    fn set(&mut self, data: u8) {
+        self.enabled = data & 0x80 != 0;
+        self.period = (data >> 4) & 0x07;
+        self.negate = data & 0x08 != 0;
+        self.shift = data & 0x07;
+        self.reload = true;
+    }
+}
+
+pub struct Pulse {
+    // the first pulse channel negates the sweep change with one's complement,
+    // the second one with two's complement
+    ones_complement: bool,
+    // pulse channels of the MMC5 have no sweep unit, low periods aren't muted
+    has_sweep: bool,
+
+    duty: u8,
+    sequence_step: u8,
+    timer_period: u16,
+    timer_counter: u16,
+
+    envelope: Envelope,
+    sweep: Sweep,
+    length_counter: LengthCounter,
+}
+
+impl Pulse {
+    pub fn new(ones_complement: bool) -> Pulse {
+        Pulse {
+            ones_complement,
+            has_sweep: true,
+
+            duty: 0,
+            sequence_step: 0,
+            timer_period: 0,
+            timer_counter: 0,
+
+            envelope: Envelope::new(),
+            sweep: Sweep::new(),
+            length_counter: LengthCounter::new(),
+        }
+    }
+
+    pub fn without_sweep() -> Pulse {
+        Pulse {
+            has_sweep: false,
+            ..Pulse::new(false)
+        }
+    }
+
+    pub fn write_register(&mut self, register: u16, data: u8) {
+        match register {
+            0 => { // DDLC VVVV
+                self.duty = data >> 6;
+                self.length_counter.set_halt(data & 0x20 != 0);
+                self.envelope.set(data);
+            },
+            1 => self.sweep.set(data),
+            2 => self.timer_period = (self.timer_period & 0x0700) | data as u16,
+            3 => { // LLLL LTTT
+                self.timer_period = (self.timer_period & 0x00FF) | ((data as u16 & 0x07) << 8);
+                self.length_counter.load(data >> 3);
+                self.envelope.restart();
+                self.sequence_step = 0;
+            },
+            _ => (),
+        }
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.length_counter.set_enabled(enabled);
+    }
+
+    pub fn active(&self) -> bool {
+        self.length_counter.active()
+    }
+
+    pub fn clock_timer(&mut self) { // every apu cycle (two cpu cycles)
+        if self.timer_counter == 0 {
+            self.timer_counter = self.timer_period;
+            self.sequence_step = (self.sequence_step + 1) & 0x07;
+        } else {
+            self.timer_counter -= 1;
+        }
+    }
+
+    pub fn clock_quarter_frame(&mut self) {
+        self.envelope.clock();
+    }
+
+    pub fn clock_half_frame(&mut self) {
+        self.length_counter.clock();
+
+        let target_period = self.sweep_target_period();
+        if self.sweep.divider == 0 && self.sweep.enabled && self.sweep.shift > 0 && !self.sweep_muted(target_period) {
+            self.timer_period = target_period;
+        }
+        if self.sweep.divider == 0 || self.sweep.reload {
+            self.sweep.divider = self.sweep.period;
+            self.sweep.reload = false;
+        } else {
+            self.sweep.divider -= 1;
+        }
+    }
+
+    fn sweep_target_period(&self) -> u16 {
+        let change = self.timer_period >> self.sweep.shift;
+        if self.sweep.negate {
+            let complement = match self.ones_complement {
+                true  => 1,
+                false => 0,
+            };
+            self.timer_period.saturating_sub(change + complement)
+        } else {
+            self.timer_period + change
+        }
+    }
+
+    fn sweep_muted(&self, target_period: u16) -> bool {
+        self.has_sweep && (self.timer_period < 8 || target_period > 0x07FF)
+    }
+
+    pub fn output(&self) -> u8 {
+        if !self.length_counter.active()
+            || self.sweep_muted(self.sweep_target_period())
+            || DUTY_TABLE[self.duty as usize][self.sequence_step as usize] == 0
+        {
+            return 0;
+        }
+        self.envelope.output()
+    }
+}
@@ -0,0 +1,91 @@
+use super::length_counter::LengthCounter;
+
+const SEQUENCE: [u8; 32] = [
+    15, 14, 13, 12, 11, 10,  9,  8,  7,  6,  5,  4,  3,  2,  1,  0,
+     0,  1,  2,  3,  4,  5,  6,  7,  8,  9, 10, 11, 12, 13, 14, 15,
+];
+
+pub struct Triangle {
+    sequence_step: u8,
+    timer_period: u16,
+    timer_counter: u16,
+
+    control_flag: bool,
+    linear_reload_value: u8,
+    linear_counter: u8,
+    linear_reload: bool,
+
+    length_counter: LengthCounter,
+}
+
+impl Triangle {
+    pub fn new() -> Triangle {
+        Triangle {
+            sequence_step: 0,
+            timer_period: 0,
+            timer_counter: 0,
+
+            control_flag: false,
+            linear_reload_value: 0,
+            linear_counter: 0,
+            linear_reload: false,
+
+            length_counter: LengthCounter::new(),
+        }
+    }
+
+    pub fn write_register(&mut self, register: u16, data: u8) {
+        match register {
+            0 => { // CRRR RRRR
+                self.control_flag = data & 0x80 != 0;
+                self.length_counter.set_halt(self.control_flag);
+                self.linear_reload_value = data & 0x7F;
+            },
+            2 => self.timer_period = (self.timer_period & 0x0700) | data as u16,
+            3 => { // LLLL LTTT
+                self.timer_period = (self.timer_period & 0x00FF) | ((data as u16 & 0x07) << 8);
+                self.length_counter.load(data >> 3);
+                self.linear_reload = true;
+            },
+            _ => (),
+        }
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.length_counter.set_enabled(enabled);
+    }
+
+    pub fn active(&self) -> bool {
+        self.length_counter.active()
+    }
+
+    pub fn clock_timer(&mut self) { // every cpu cycle
+        if self.timer_counter == 0 {
+            self.timer_counter = self.timer_period;
+            if self.linear_counter > 0 && self.length_counter.active() {
+                self.sequence_step = (self.sequence_step + 1) & 0x1F;
+            }
+        } else {
+            self.timer_counter -= 1;
+        }
+    }
+
+    pub fn clock_quarter_frame(&mut self) {
+        if self.linear_reload {
+            self.linear_counter = self.linear_reload_value;
+        } else if self.linear_counter > 0 {
+            self.linear_counter -= 1;
+        }
+        if !self.control_flag {
+            self.linear_reload = false;
+        }
+    }
+
+    pub fn clock_half_frame(&mut self) {
+        self.length_counter.clock();
+    }
+
+    pub fn output(&self) -> u8 {
+        SEQUENCE[self.sequence_step as usize]
+    }
+}
@@ -1,40 +1,38 @@
 use std::rc::Rc;
 use std::cell::RefCell;
 
+mod dma;
+
+use dma::{Dma, DmaCycle};
 use crate::program::Cartridge;
 use crate::ppu::Ppu;
+use crate::apu::Apu;
 use crate::environment::control::Controller;
 
 pub struct Bus {
     cpu_ram: [u8; 0x0800],
     ppu: Rc<RefCell<Ppu>>,
+    apu: Rc<RefCell<Apu>>,
     controller_a: Controller,
     cartridge: Option<Rc<RefCell<Cartridge>>>,
 
     previous_data: u8,
 
-    dma_enable: bool,
-    dma_wait_clock: bool,
-    oam_page: u16,
-    oam_addr: u8,
-    oam_data: u8,
+    dma: Dma,
 }
 
 impl Bus {
-    pub fn new(controller_a: Controller, ppu: Rc<RefCell<Ppu>>) -> Bus {
+    pub fn new(controller_a: Controller, ppu: Rc<RefCell<Ppu>>, apu: Rc<RefCell<Apu>>) -> Bus {
         Bus {
             cpu_ram: [0; 0x0800],
             ppu,
+            apu,
             controller_a,
             cartridge: None,
 
             previous_data: 0,
 
-            dma_enable: false,
-            dma_wait_clock: true,
-            oam_page: 0,
-            oam_addr: 0,
-            oam_data: 0,
+            dma: Dma::new(),
         }
     }
 
@@ -50,13 +48,15 @@ impl Bus {
         } else if address >= 0x2000 && address <= 0x3FFF {
             data = self.ppu.borrow().
                 cpu_read_only(address & 0x0007);
+        } else if address == 0x4015 {
+            data = self.apu.borrow().peek_status();
         } else if address == 0x4016 {
             data = self.controller_a.read_register();
         } else if address == 0x4017 {
             data = 0;
         } else if address >= 0x4020 {
-            self.cartridge.as_ref().unwrap().borrow_mut().
-                read_prg_rom(address, &mut data);
+            self.cartridge.as_ref().unwrap().borrow().
+                peek_prg_rom(address, &mut data);
         }
         data
     }
@@ -68,6 +68,8 @@ impl Bus {
         } else if address >= 0x2000 && address <= 0x3FFF {
             data = self.ppu.borrow_mut().
                 cpu_read(address & 0x0007);
+        } else if address == 0x4015 {
+            data = self.apu.borrow_mut().read_status();
         } else if address == 0x4016 {
             data = self.controller_a.read_bit();
         } else if address == 0x4017 {
@@ -87,10 +89,13 @@ impl Bus {
         } else if address >= 0x2000 && address <= 0x3FFF {
             self.ppu.borrow_mut().
                 cpu_write(address & 0x0007, data);
+            if let Some(cartridge) = self.cartridge.as_ref() {
+                cartridge.borrow_mut().ppu_register_write(0x2000 | (address & 0x0007), data);
+            }
+        } else if (address >= 0x4000 && address <= 0x4013) || address == 0x4015 || address == 0x4017 {
+            self.apu.borrow_mut().write_register(address, data);
         } else if address == 0x4014 {
-            self.dma_enable = true;
-            self.dma_wait_clock = true;
-            self.oam_page = (data as u16) << 8;
+            self.dma.start_oam(data);
         } else if address == 0x4016 {
             self.controller_a.update_register_by_input();
         } else if address >= 0x4020 {
@@ -103,32 +108,56 @@ impl Bus {
         self.controller_a.update_register(input_value);
     }
 
-    pub fn dma_enable(&self) -> bool {
-        self.dma_enable
-    }
-
-    pub fn disable_dma(&mut self) {
-        self.dma_enable = false;
+    pub fn irq_line(&self) -> bool {
+        let cartridge_irq = match self.cartridge.as_ref() {
+            Some(cartridge) => cartridge.borrow().irq_pending(),
+            None => false,
+        };
+        self.apu.borrow().irq() || cartridge_irq
     }
 
-    pub fn dma_wait_clock(&self) -> bool {
-        self.dma_wait_clock
+    pub fn cartridge_audio_output(&self) -> f32 {
+        match self.cartridge.as_ref() {
+            Some(cartridge) => cartridge.borrow().audio_output(),
+            None => 0.0,
+        }
     }
 
-    pub fn set_dma_wait_clock(&mut self, wait: bool) {
-        self.dma_wait_clock = wait;
+    pub fn clock_cartridge(&mut self) {
+        if let Some(cartridge) = self.cartridge.as_ref() {
+            cartridge.borrow_mut().clock();
+        }
     }
 
-    pub fn read_dma_byte(&mut self) {
-        let address = self.oam_page | self.oam_addr as u16;
-        self.oam_data = self.read_cpu_ram(address);
+    pub fn acknowledge_cartridge_irq(&mut self) {
+        if let Some(cartridge) = self.cartridge.as_ref() {
+            cartridge.borrow_mut().irq_acknowledge();
+        }
     }
 
-    pub fn write_dma_byte(&mut self) {
-        self.ppu.borrow_mut().write_oam_byte(self.oam_addr, self.oam_data);
-        self.oam_addr = self.oam_addr.wrapping_add(1);
-        if self.oam_addr == 0x00 {
-            self.dma_enable = false;
+    // returns true when the cpu is halted by DMA for this cycle
+    pub fn clock_dma(&mut self) -> bool {
+        if !self.dma.dmc_pending() {
+            let request = self.apu.borrow().dmc_sample_request();
+            if let Some(address) = request {
+                self.dma.request_dmc(address);
+            }
+        }
+        match self.dma.clock() {
+            DmaCycle::Cpu => return false,
+            DmaCycle::Halt => (),
+            DmaCycle::DmcRead(address) => {
+                let data = self.read_cpu_ram(address);
+                self.apu.borrow_mut().load_dmc_sample(data);
+            },
+            DmaCycle::OamRead(address) => {
+                let data = self.read_cpu_ram(address);
+                self.dma.set_oam_data(data);
+            },
+            DmaCycle::OamWrite(oam_addr, data) => {
+                self.ppu.borrow_mut().write_oam_byte(oam_addr, data);
+            },
         }
+        true
     }
 }
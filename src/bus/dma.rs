@@ -0,0 +1,107 @@
+// DMA arbiter for OAM DMA ($4014) and DMC sample fetches.
+// cpu cycles alternate between get (read) and put (write) cycles, both units read
+// only on get cycles, the DMC fetch has priority over the OAM transfer.
+
+pub enum DmaCycle {
+    Cpu,             // no DMA, the cpu owns the bus
+    Halt,            // the cpu is halted, nothing is transferred (halt, dummy or alignment cycle)
+    DmcRead(u16),
+    OamRead(u16),
+    OamWrite(u8, u8), // oam address and data
+}
+
+pub struct Dma {
+    get_cycle: bool,
+    halted: bool,
+
+    oam_active: bool,
+    oam_page: u16,
+    oam_addr: u8,
+    oam_data: Option<u8>,
+
+    dmc_address: Option<u16>,
+    dmc_delay: u8, // halt and dummy cycles before the DMC can take a get cycle
+}
+
+impl Dma {
+    pub fn new() -> Dma {
+        Dma {
+            get_cycle: false,
+            halted: false,
+
+            oam_active: false,
+            oam_page: 0,
+            oam_addr: 0,
+            oam_data: None,
+
+            dmc_address: None,
+            dmc_delay: 0,
+        }
+    }
+
+    pub fn start_oam(&mut self, page: u8) {
+        self.oam_active = true;
+        self.oam_page = (page as u16) << 8;
+        self.oam_addr = 0;
+        self.oam_data = None;
+    }
+
+    pub fn request_dmc(&mut self, address: u16) {
+        if self.dmc_address.is_none() {
+            self.dmc_address = Some(address);
+            self.dmc_delay = 2;
+        }
+    }
+
+    pub fn dmc_pending(&self) -> bool {
+        self.dmc_address.is_some()
+    }
+
+    pub fn set_oam_data(&mut self, data: u8) {
+        self.oam_data = Some(data);
+    }
+
+    pub fn clock(&mut self) -> DmaCycle { // every cpu cycle
+        let get_cycle = self.get_cycle;
+        self.get_cycle = !self.get_cycle;
+
+        if !self.oam_active && self.dmc_address.is_none() {
+            self.halted = false;
+            return DmaCycle::Cpu;
+        }
+
+        if !self.halted {
+            self.halted = true;
+            if self.dmc_delay > 0 {
+                self.dmc_delay -= 1;
+            }
+            return DmaCycle::Halt;
+        }
+
+        if let Some(address) = self.dmc_address {
+            if self.dmc_delay > 0 {
+                self.dmc_delay -= 1;
+            } else if get_cycle {
+                self.dmc_address = None;
+                return DmaCycle::DmcRead(address);
+            }
+        }
+
+        if self.oam_active {
+            match (get_cycle, self.oam_data) {
+                (true, None) => return DmaCycle::OamRead(self.oam_page | self.oam_addr as u16),
+                (false, Some(data)) => {
+                    let oam_addr = self.oam_addr;
+                    self.oam_data = None;
+                    self.oam_addr = self.oam_addr.wrapping_add(1);
+                    if self.oam_addr == 0x00 {
+                        self.oam_active = false;
+                    }
+                    return DmaCycle::OamWrite(oam_addr, data);
+                },
+                _ => (),
+            }
+        }
+        DmaCycle::Halt
+    }
+}
@@ -69,6 +69,8 @@ pub struct Emu6502 {
     cycle_counter: u8,
     additional_cycles: u8,
 
+    irq_line: bool,
+
     bus: Rc<RefCell<Bus>>,
     pub clock_complete: bool,
     pub debug: bool,
@@ -95,6 +97,8 @@ impl Emu6502 {
             cycle_counter: 0,
             additional_cycles: 0,
 
+            irq_line: false,
+
             bus: bus.clone(),
             clock_complete: false,
             debug: false
@@ -109,8 +113,15 @@ impl Emu6502 {
         self.prog_counter
     }
 
+    pub fn set_irq_line(&mut self, level: bool) {
+        self.irq_line = level;
+    }
+
     pub fn clock(&mut self) {
-        if self.cycle_counter == 0 {
+        if self.cycle_counter == 0 && self.irq_line && self.get_flag(Flag::I) == 0 {
+            // irq line is level triggered and polled between instructions
+            self.irq();
+        } else if self.cycle_counter == 0 {
             self.additional_cycles = 0;
             self.opcode = self.read_data(self.prog_counter);
             let op = &OPCODES[self.opcode as usize];
@@ -157,13 +168,17 @@ impl Emu6502 {
             self.push_to_stack(high);
             self.push_to_stack(low);
             self.set_flag(Flag::B, false);
-            self.set_flag(Flag::I, true);
             self.set_flag(Flag::U, true);
+            // the pushed status keeps the interrupt flag as it was, so RTI enables irqs again
             self.push_to_stack(self.status);
+            self.set_flag(Flag::I, true);
             let new_low = self.read_data(0xFFFE);
             let new_high = self.read_data(0xFFFF);
             self.prog_counter = ((new_high as u16) << 8) | new_low as u16;
             self.cycle_counter = 7;
+            if self.debug {
+                info!("cpu: irq executing, new prog_counter: {:04X}", self.prog_counter);
+            }
         }
     }
 
@@ -174,8 +189,8 @@ impl Emu6502 {
         self.push_to_stack(low);
         self.set_flag(Flag::B, false);
         self.set_flag(Flag::U, true);
-        self.set_flag(Flag::I, true);
         self.push_to_stack(self.status);
+        self.set_flag(Flag::I, true);
         let new_low = self.read_data(0xFFFA);
         let new_high = self.read_data(0xFFFB);
         self.prog_counter = ((new_high as u16) << 8) | new_low as u16;
@@ -728,9 +743,9 @@ impl Emu6502 {
         self.push_to_stack(high);
         self.push_to_stack(low);
         self.set_flag(Flag::U, true);
-        self.set_flag(Flag::I, true);
         self.set_flag(Flag::B, true);
         self.push_to_stack(self.status);
+        self.set_flag(Flag::I, true);
         let new_low = self.read_data(0xFFFE);
         let new_high = self.read_data(0xFFFF);
         self.prog_counter = ((new_high as u16) << 8) | new_low as u16;
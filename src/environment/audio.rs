@@ -0,0 +1,78 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Seek, SeekFrom, Write};
+
+pub trait AudioSink {
+    fn sample_rate(&self) -> u32;
+    fn write_samples(&mut self, samples: &[i16]) -> io::Result<()>;
+    fn flush(&mut self) -> io::Result<()>;
+}
+
+// mono 16 bit PCM wav file, the header sizes are updated on every flush
+pub struct WavSink {
+    writer: BufWriter<File>,
+    sample_rate: u32,
+    data_size: u32,
+}
+
+impl WavSink {
+    pub fn create(file_name: &str, sample_rate: u32) -> io::Result<WavSink> {
+        let mut writer = BufWriter::new(File::create(file_name)?);
+        WavSink::write_header(&mut writer, sample_rate, 0)?;
+        Ok(WavSink {
+            writer,
+            sample_rate,
+            data_size: 0,
+        })
+    }
+
+    fn write_header(writer: &mut BufWriter<File>, sample_rate: u32, data_size: u32) -> io::Result<()> {
+        let channels: u16 = 1;
+        let bits_per_sample: u16 = 16;
+        let block_align = channels * bits_per_sample / 8;
+        let byte_rate = sample_rate * block_align as u32;
+
+        writer.write_all(b"RIFF")?;
+        writer.write_all(&(36 + data_size).to_le_bytes())?;
+        writer.write_all(b"WAVE")?;
+        writer.write_all(b"fmt ")?;
+        writer.write_all(&16u32.to_le_bytes())?;
+        writer.write_all(&1u16.to_le_bytes())?; // PCM
+        writer.write_all(&channels.to_le_bytes())?;
+        writer.write_all(&sample_rate.to_le_bytes())?;
+        writer.write_all(&byte_rate.to_le_bytes())?;
+        writer.write_all(&block_align.to_le_bytes())?;
+        writer.write_all(&bits_per_sample.to_le_bytes())?;
+        writer.write_all(b"data")?;
+        writer.write_all(&data_size.to_le_bytes())?;
+        Ok(())
+    }
+}
+
+impl AudioSink for WavSink {
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn write_samples(&mut self, samples: &[i16]) -> io::Result<()> {
+        for sample in samples {
+            self.writer.write_all(&sample.to_le_bytes())?;
+        }
+        self.data_size += (samples.len() * 2) as u32;
+        Ok(())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.seek(SeekFrom::Start(0))?;
+        WavSink::write_header(&mut self.writer, self.sample_rate, self.data_size)?;
+        self.writer.seek(SeekFrom::End(0))?;
+        self.writer.flush()
+    }
+}
+
+impl Drop for WavSink {
+    fn drop(&mut self) {
+        if let Err(e) = self.flush() {
+            error!("can't finalize wav file: {}", e);
+        }
+    }
+}
@@ -1,2 +1,5 @@
 pub mod screen;
 pub mod control;
+pub mod audio;
+pub mod resampler;
+pub mod font;
@@ -0,0 +1,102 @@
+use std::f64::consts::PI;
+
+// band-limited step synthesis (blip-buffer like): every change of the input level
+// is added to the output as a windowed sinc impulse and the result is integrated
+
+const PHASES: usize = 32;
+const KERNEL_WIDTH: usize = 16;
+const CUTOFF: f64 = 0.45; // fraction of the output sample rate
+const HIGH_PASS_FREQUENCY: f64 = 90.0;
+const GAIN: f32 = 30000.0;
+
+pub struct Resampler {
+    ratio: f64, // output samples per input sample
+    time: f64,  // position of the next input sample in the output buffer
+    last_input: f32,
+    kernel: Vec<[f32; KERNEL_WIDTH]>,
+    buffer: Vec<f32>,
+
+    integrator: f32,
+    high_pass: f32,
+    high_pass_factor: f32,
+}
+
+impl Resampler {
+    pub fn new(clock_rate: f64, sample_rate: u32) -> Resampler {
+        let kernel = (0..PHASES).map(|phase| {
+            let offset = phase as f64 / PHASES as f64;
+            let center = (KERNEL_WIDTH / 2) as f64 - 1.0 + offset;
+            let mut taps = [0.0; KERNEL_WIDTH];
+            let mut sum = 0.0;
+            for (i, tap) in taps.iter_mut().enumerate() {
+                let x = i as f64 - center;
+                let sinc = match x == 0.0 {
+                    true  => 1.0,
+                    false => (2.0 * PI * CUTOFF * x).sin() / (2.0 * PI * CUTOFF * x),
+                };
+                let w = 2.0 * PI * x / KERNEL_WIDTH as f64;
+                let window = 0.42 + 0.5 * w.cos() + 0.08 * (2.0 * w).cos();
+                let value = sinc * window;
+                *tap = value as f32;
+                sum += value;
+            }
+            taps.iter_mut().for_each(|tap| *tap /= sum as f32);
+            taps
+        }).collect();
+
+        Resampler {
+            ratio: sample_rate as f64 / clock_rate,
+            time: 0.0,
+            last_input: 0.0,
+            kernel,
+            buffer: vec![0.0; KERNEL_WIDTH],
+
+            integrator: 0.0,
+            high_pass: 0.0,
+            high_pass_factor: (1.0 - (-2.0 * PI * HIGH_PASS_FREQUENCY / sample_rate as f64).exp()) as f32,
+        }
+    }
+
+    // input samples go at the clock rate, e.g. Apu::pull_samples
+    pub fn push(&mut self, input: &[f32]) {
+        for &sample in input {
+            let delta = sample - self.last_input;
+            if delta != 0.0 {
+                self.add_delta(delta);
+                self.last_input = sample;
+            }
+            self.time += self.ratio;
+        }
+    }
+
+    fn add_delta(&mut self, delta: f32) {
+        let position = self.time.floor();
+        let phase = ((self.time - position) * PHASES as f64) as usize;
+        let start = position as usize;
+        if self.buffer.len() < start + KERNEL_WIDTH {
+            self.buffer.resize(start + KERNEL_WIDTH, 0.0);
+        }
+        let taps = &self.kernel[phase.min(PHASES - 1)];
+        for (value, tap) in self.buffer[start..start + KERNEL_WIDTH].iter_mut().zip(taps.iter()) {
+            *value += delta * tap;
+        }
+    }
+
+    // output samples that can't be changed by further input anymore
+    pub fn read_samples(&mut self) -> Vec<i16> {
+        let available = (self.time.floor() as usize).min(self.buffer.len());
+        let mut output = Vec::with_capacity(available);
+        for &delta in &self.buffer[..available] {
+            self.integrator += delta;
+            self.high_pass += (self.integrator - self.high_pass) * self.high_pass_factor;
+            let sample = (self.integrator - self.high_pass) * GAIN;
+            output.push(sample.max(i16::MIN as f32).min(i16::MAX as f32) as i16);
+        }
+        self.buffer.drain(..available);
+        if self.buffer.len() < KERNEL_WIDTH {
+            self.buffer.resize(KERNEL_WIDTH, 0.0);
+        }
+        self.time -= available as f64;
+        output
+    }
+}
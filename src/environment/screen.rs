@@ -4,6 +4,8 @@ use spriter::window::Window;
 use spriter::Canvas;
 use spriter::Color;
 
+use super::font::{self, GLYPH_WIDTH};
+
 pub struct Screen {
     main_area: Area,
     sprite_area_left: Area,
@@ -11,8 +13,14 @@ pub struct Screen {
     main_color: Area,
     background_pallettes: Vec<Area>,
     sprite_pallettes: Vec<Area>,
+    waveforms: Vec<Area>,
 }
 
+const WAVEFORM_WIDTH: u32 = 128;
+const WAVEFORM_HEIGHT: u32 = 16;
+const WAVEFORM_BACKGROUND: u32 = 0x111111;
+const WAVEFORM_COLORS: [u32; 5] = [0xDDCCAA, 0xAACCDD, 0x55AA99, 0xAAAAAA, 0xCC7766];
+
 impl Screen {
     pub fn new(window: &mut Window, pixel_size: u32, ) -> Screen {
         let main_screen_width = 256;
@@ -77,13 +85,26 @@ impl Screen {
             return Area::new(sprite_pallette_canvas, 4, 1);
         }).collect();
 
+        // oscilloscope view of the apu channels, to the right of the pallettes
+        let waveforms: Vec<Area> = (0..WAVEFORM_COLORS.len() as u32).map(|i| {
+            let waveform_canvas = window.create_canvas(
+                main_screen_width * pixel_size + 20 + 84 * pixel_size,
+                pattern_tabel_width * pixel_size + 20 + 11 * pixel_size + (WAVEFORM_HEIGHT + 1) * pixel_size * i,
+                WAVEFORM_WIDTH * pixel_size,
+                WAVEFORM_HEIGHT * pixel_size,
+                WAVEFORM_WIDTH,
+                WAVEFORM_HEIGHT);
+            return Area::new(waveform_canvas, WAVEFORM_WIDTH, WAVEFORM_HEIGHT);
+        }).collect();
+
         Screen {
             main_area,
             sprite_area_left,
             sprite_area_right,
             main_color,
             background_pallettes,
-            sprite_pallettes
+            sprite_pallettes,
+            waveforms,
          }
     }
 
@@ -110,6 +131,57 @@ impl Screen {
     pub fn set_point_at_sprite_color_area(&mut self, pallette_id: usize, color: u32) {
         self.sprite_pallettes[pallette_id].set_next_point(color);
     }
+
+    pub fn fill_main_area(&mut self, color: u32) {
+        self.main_area.fill(color);
+    }
+
+    // text in the 3x5 font, every pixel of the font is a square of `scale` pixels of the area
+    pub fn draw_text_at_main_area(&mut self, x: usize, y: usize, text: &str, scale: usize, color: u32) {
+        let area = &mut self.main_area;
+        for (idx, c) in text.chars().enumerate() {
+            let glyph_x = x + idx * (GLYPH_WIDTH + 1) * scale;
+            for (row, bits) in font::glyph(c).iter().enumerate() {
+                for column in 0..GLYPH_WIDTH {
+                    if bits & (1 << (GLYPH_WIDTH - 1 - column)) == 0 {
+                        continue;
+                    }
+                    for dy in 0..scale {
+                        for dx in 0..scale {
+                            let point_x = glyph_x + column * scale + dx;
+                            let point_y = y + row * scale + dy;
+                            if point_x < area.width && point_y < area.height {
+                                area.set_point(point_x, point_y, color);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    // samples are in range 0.0 - 1.0 and are stretched on the width of the area
+    pub fn draw_waveform(&mut self, channel: usize, samples: &[f32]) {
+        let area = &mut self.waveforms[channel];
+        area.fill(WAVEFORM_BACKGROUND);
+        if samples.is_empty() {
+            return;
+        }
+        let color = WAVEFORM_COLORS[channel];
+        let max_y = (area.height - 1) as f32;
+        let mut previous_y = None;
+        for x in 0..area.width {
+            let sample = samples[x * samples.len() / area.width].max(0.0).min(1.0);
+            let y = (max_y - sample * max_y).round() as usize;
+            let (from, to) = match previous_y {
+                Some(previous_y) if previous_y < y => (previous_y, y),
+                Some(previous_y) => (y, previous_y),
+                None => (y, y),
+            };
+            (from..=to).for_each(|y| area.set_point(x, y, color));
+            previous_y = Some(y);
+        }
+    }
 }
 
 struct Area {
@@ -127,6 +199,18 @@ impl Area {
         Area { canvas, width, height, x: 0, y: 0 }
     }
 
+    fn set_point(&mut self, x: usize, y: usize, color: u32) {
+        _ = self.canvas.set_pixel(x as i32, y as i32, Color::from_u32(color));
+    }
+
+    fn fill(&mut self, color: u32) {
+        for y in 0..self.height {
+            for x in 0..self.width {
+                self.set_point(x, y, color);
+            }
+        }
+    }
+
     fn set_next_point(&mut self, color: u32) {
         _ = self.canvas.set_pixel(self.x as i32, self.y as i32, Color::from_u32(color));
         self.x += 1;
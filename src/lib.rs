@@ -5,6 +5,7 @@ extern crate log;
 
 pub mod emu6502;
 pub mod ppu;
+pub mod apu;
 pub mod bus;
 pub mod program;
 pub mod environment;
@@ -11,35 +11,60 @@ use std::cell::RefCell;
 use std::io::{stdin, stdout, Write};
 use std::time::Duration;
 
+// battery-backed ram is written to disk every 10 seconds of emulation if it was changed
+const SAVE_PERIOD: u32 = 600; // frames
+const FRAME_RATE: u32 = 60;
+
 use emu::emu6502::Emu6502;
 use emu::ppu::Ppu;
+use emu::apu::{self, Apu, Channel};
 use emu::bus::Bus;
-use emu::program::Cartridge;
+use emu::program::{Cartridge, NsfHeader};
 use emu::environment::screen::Screen;
 use emu::environment::control::Controller;
+use emu::environment::audio::{AudioSink, WavSink};
+use emu::environment::resampler::Resampler;
 
 struct Device {
     screen: Screen,
     cpu: Emu6502,
     ppu: Rc<RefCell<Ppu>>,
+    apu: Rc<RefCell<Apu>>,
     bus: Rc<RefCell<Bus>>,
+    cartridge: Option<Rc<RefCell<Cartridge>>>,
+    audio_sink: Option<Box<dyn AudioSink>>,
+    resampler: Option<Resampler>,
     clock_type: ClockType,
     clock_counter: u32,
+    save_counter: u32,
+    disk_side: usize,
+    // nsf tunes are played without the ppu picture, the main area shows the track instead
+    nsf: Option<NsfHeader>,
+    track_frames: u32,
 }
 
 impl Device {
     fn new (screen: Screen) -> Device {
         let controller_a = Controller::new();
         let ppu = Rc::new(RefCell::new(Ppu::new()));
-        let bus = Rc::new(RefCell::new(Bus::new(controller_a, ppu.clone())));
+        let apu = Rc::new(RefCell::new(Apu::new()));
+        let bus = Rc::new(RefCell::new(Bus::new(controller_a, ppu.clone(), apu.clone())));
         let cpu = Emu6502::new(bus.clone());
         Device {
             screen,
             cpu,
             ppu,
+            apu,
             bus,
+            cartridge: None,
+            audio_sink: None,
+            resampler: None,
             clock_type: ClockType::Undefined,
             clock_counter: 0,
+            save_counter: 0,
+            disk_side: 1,
+            nsf: None,
+            track_frames: 0,
         }
     }
 
@@ -47,9 +72,94 @@ impl Device {
         let cartridge = Rc::new(RefCell::new(cartridge));
         self.bus.borrow_mut().insert_cartridge(cartridge.clone());
         self.ppu.borrow_mut().insert_cartridge(cartridge.clone());
+        self.nsf = cartridge.borrow().nsf_header().cloned();
+        self.cartridge = Some(cartridge);
         self.cpu.reset();
     }
 
+    fn save_cartridge(&mut self) {
+        if let Some(cartridge) = self.cartridge.as_ref() {
+            if let Err(e) = cartridge.borrow_mut().save() {
+                error!("can't save prg ram: {}", e);
+            }
+        }
+    }
+
+    fn update_save(&mut self) {
+        self.save_counter += 1;
+        if self.save_counter == SAVE_PERIOD {
+            self.save_counter = 0;
+            self.save_cartridge();
+        }
+    }
+
+    fn set_audio_sink(&mut self, audio_sink: Box<dyn AudioSink>) {
+        self.resampler = Some(Resampler::new(apu::CPU_FREQUENCY, audio_sink.sample_rate()));
+        self.audio_sink = Some(audio_sink);
+    }
+
+    fn update_audio(&mut self) {
+        let samples = self.apu.borrow_mut().pull_samples();
+        if let (Some(resampler), Some(audio_sink)) = (self.resampler.as_mut(), self.audio_sink.as_mut()) {
+            resampler.push(&samples);
+            if let Err(e) = audio_sink.write_samples(&resampler.read_samples()) {
+                error!("audio output error: {}", e);
+                self.audio_sink = None;
+            }
+        }
+    }
+
+    fn flush_audio(&mut self) {
+        if let Some(audio_sink) = self.audio_sink.as_mut() {
+            if let Err(e) = audio_sink.flush() {
+                error!("audio output error: {}", e);
+            }
+        }
+    }
+
+    fn update_waveforms(&mut self) {
+        for (idx, channel) in Channel::ALL.iter().enumerate() {
+            let max_output = channel.max_output() as f32;
+            let samples: Vec<f32> = self.apu.borrow().waveform(*channel).iter()
+                .map(|output| *output as f32 / max_output)
+                .collect();
+            self.screen.draw_waveform(idx, &samples);
+        }
+        self.apu.borrow_mut().clear_waveforms();
+    }
+
+    // commands: "mute <channel>", "solo [channel]", "volume <channel> <value>"
+    // channel is 1 - 5 (pulse 1, pulse 2, triangle, noise, dmc)
+    fn apu_command(&mut self, input: &str) {
+        let mut input_parts = input.split_whitespace();
+        let command = input_parts.next().unwrap_or("");
+        let channel = input_parts.next()
+            .and_then(|idx| idx.parse::<usize>().ok())
+            .and_then(|idx| Channel::ALL.get(idx.wrapping_sub(1)).copied());
+        let mut apu = self.apu.borrow_mut();
+        match (command, channel) {
+            ("mute", Some(channel)) => {
+                let muted = !apu.is_muted(channel);
+                apu.set_muted(channel, muted);
+                info!("apu: {:?} muted: {}", channel, muted);
+            },
+            ("solo", channel) => {
+                apu.set_solo(channel);
+                info!("apu: solo {:?}", channel);
+            },
+            ("volume", Some(channel)) => {
+                match input_parts.next().and_then(|volume| volume.parse::<f32>().ok()) {
+                    Some(volume) => {
+                        apu.set_volume(channel, volume);
+                        info!("apu: {:?} volume: {}", channel, apu.volume(channel));
+                    },
+                    None => info!("volume must be a number"),
+                }
+            },
+            _ => info!("unknown apu command: {}", input.trim()),
+        }
+    }
+
     fn print_memory_by_address(&self, address: u16, offset: u16) {
         let min = address.saturating_sub(offset);
         let max = address.saturating_add(offset + 1);
@@ -76,21 +186,16 @@ impl Device {
     fn clock(&mut self) {
         let color = self.ppu.borrow_mut().clock();
         if self.clock_counter % 3 == 0 {
-            if !self.bus.borrow().dma_enable() {
+            let irq_line = self.bus.borrow().irq_line();
+            self.cpu.set_irq_line(irq_line);
+            let cpu_halted = self.bus.borrow_mut().clock_dma();
+            if !cpu_halted {
                 self.cpu.clock();
-            } else {
-                if self.bus.borrow().dma_wait_clock() {
-                    if self.clock_counter % 2 == 1 {
-                        self.bus.borrow_mut().set_dma_wait_clock(false);
-                    }
-                } else {
-                    if self.clock_counter % 2 == 0 {
-                        self.bus.borrow_mut().read_dma_byte();
-                    } else {
-                        self.bus.borrow_mut().write_dma_byte();
-                    }
-                }
             }
+            self.apu.borrow_mut().clock();
+            self.bus.borrow_mut().clock_cartridge();
+            let expansion_output = self.bus.borrow().cartridge_audio_output();
+            self.apu.borrow_mut().set_expansion_output(expansion_output);
         }
         if self.ppu.borrow().nmi_require() {
             self.cpu.nmi();
@@ -99,13 +204,84 @@ impl Device {
         self.clock_counter = self.clock_counter.wrapping_add(1);
 
         match color {
-            Some(color) => self.screen.set_point_at_main_area(color),
-            None => ()
+            Some(color) if self.nsf.is_none() => self.screen.set_point_at_main_area(color),
+            _ => ()
         }
 
         self.update_pallettes();
     }
 
+    // disk system: ejects the disk, the next press inserts the next side
+    fn switch_disk(&mut self) {
+        if let Some(cartridge) = self.cartridge.as_ref() {
+            let mut cartridge = cartridge.borrow_mut();
+            let sides = cartridge.disk_sides();
+            if sides == 0 {
+                return;
+            }
+            let side = match cartridge.disk_side() {
+                Some(_) => None,
+                None => Some(self.disk_side % sides),
+            };
+            if side.is_some() {
+                self.disk_side += 1;
+            }
+            cartridge.insert_disk(side);
+            match side {
+                Some(side) => info!("disk: side {} of {} inserted", side + 1, sides),
+                None => info!("disk: ejected"),
+            }
+        }
+    }
+
+    // previous or next nsf track, the driver starts it after the reset
+    fn change_track(&mut self, step: isize) {
+        let songs = match self.nsf.as_ref() {
+            Some(nsf) => nsf.songs as isize,
+            None => return,
+        };
+        if let Some(cartridge) = self.cartridge.as_ref() {
+            let mut cartridge = cartridge.borrow_mut();
+            let track = cartridge.track().unwrap_or(0) as isize;
+            let track = (track + step).rem_euclid(songs) as usize;
+            cartridge.select_track(track);
+            info!("nsf: track {} of {}", track + 1, songs);
+        }
+        self.apu.borrow_mut().reset();
+        self.bus.borrow_mut().acknowledge_cartridge_irq();
+        self.cpu.reset();
+        self.track_frames = 0;
+    }
+
+    fn update_track_display(&mut self) {
+        let nsf = match self.nsf.as_ref() {
+            Some(nsf) => nsf,
+            None => return,
+        };
+        let track = self.cartridge.as_ref()
+            .and_then(|cartridge| cartridge.borrow().track())
+            .unwrap_or(0);
+        let seconds = self.track_frames / FRAME_RATE;
+        self.track_frames += 1;
+
+        let screen = &mut self.screen;
+        screen.fill_main_area(0x111111);
+        screen.draw_text_at_main_area(16, 24, &nsf.name, 2, 0xDDCCAA);
+        screen.draw_text_at_main_area(16, 40, &nsf.artist, 2, 0xAACCDD);
+        screen.draw_text_at_main_area(16, 56, &nsf.copyright, 2, 0xAAAAAA);
+        let track = format!("TRACK {:02}/{:02}", track + 1, nsf.songs);
+        screen.draw_text_at_main_area(16, 96, &track, 3, 0xFFFFFF);
+        let time = format!("TIME {:02}:{:02}", seconds / 60, seconds % 60);
+        screen.draw_text_at_main_area(16, 128, &time, 2, 0x55AA99);
+        let mut chips = nsf.expansion_names().join(" ");
+        if chips.is_empty() {
+            chips.push_str("NONE");
+        }
+        screen.draw_text_at_main_area(16, 152, &format!("EXPANSION {}", chips), 2, 0xAAAAAA);
+        screen.draw_text_at_main_area(16, 168, if nsf.pal { "PAL" } else { "NTSC" }, 2, 0xAAAAAA);
+        screen.draw_text_at_main_area(16, 216, "LEFT/RIGHT - TRACK   R - RESTART", 1, 0x777777);
+    }
+
     fn update_pallettes(&mut self) {
         if self.ppu.borrow().update_pallettes {
             let main_color_addr = self.ppu.borrow().read_ppu(0x3F00);
@@ -127,7 +303,11 @@ impl Device {
     }
 
     fn handle_keys(&mut self) {
-        if_pressed!(Key::Escape, {spriter::program_stop()});
+        if_pressed!(Key::Escape, {
+            self.flush_audio();
+            self.save_cartridge();
+            spriter::program_stop()
+        });
         if_pressed!(Key::C, {self.clock_type = ClockType::Manual});
         if_pressed!(Key::A, {
             let clock_type = self.clock_type;
@@ -149,9 +329,17 @@ impl Device {
         });
         if_pressed!(Key::R, {
             self.ppu.borrow_mut().reset();
+            self.apu.borrow_mut().reset();
+            self.bus.borrow_mut().acknowledge_cartridge_irq();
             self.cpu.reset();
+            self.track_frames = 0;
         });
+        if self.nsf.is_some() {
+            if_pressed!(Key::Left, {self.change_track(-1)});
+            if_pressed!(Key::Right, {self.change_track(1)});
+        }
         if_pressed!(Key::D, {self.cpu.debug = !self.cpu.debug});
+        if_pressed!(Key::I, {self.switch_disk()});
         if_pressed!(Key::E, {
             let debug = self.ppu.borrow().debug;
             self.ppu.borrow_mut().debug = !debug;
@@ -176,6 +364,12 @@ impl Device {
             self.bus.borrow_mut().write_cpu_ram(address, data);
             info!("write: {:04X} {:02X}", address, data);
         });
+        if_pressed!(Key::M, {
+            let mut input = String::new();
+            stdout().flush().unwrap();
+            stdin().read_line(&mut input).unwrap();
+            self.apu_command(&input);
+        });
         if_pressed!(Key::P, {
             let mut input = String::new();
             stdout().flush().unwrap();
@@ -201,16 +395,52 @@ enum ClockType {
 fn main() {
     env_logger::init();
 
+    // [rom] is a .nes, .unf, .fds or .nsf file (af.nes by default),
+    // --wav <file> writes the sound to a wav file, --sample-rate <hz> sets its rate (44100 by default),
+    // --patch <file> applies an ips / bps / ups patch to the rom
+    let mut rom_file = String::from("af.nes");
+    let mut wav_file = None;
+    let mut patch_file = None;
+    let mut sample_rate = 44100;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--wav" => wav_file = args.next(),
+            "--patch" => patch_file = args.next(),
+            "--sample-rate" => {
+                sample_rate = args.next().and_then(|rate| rate.parse().ok()).unwrap_or(sample_rate);
+            },
+            _ if !arg.starts_with("--") => rom_file = arg,
+            _ => warn!("unknown argument: {}", arg),
+        }
+    }
+
     let pixel_size = 3;
     let width = 522 * pixel_size;
     let height = 242 * pixel_size;
     let (runner, mut window) = spriter::init("mayonnaise", width, height);
     let screen = Screen::new(&mut window, pixel_size);
 
-    let cart = Cartridge::new("af.nes");
+    let cart = match patch_file {
+        Some(patch_file) => Cartridge::from_path_with_patch(&rom_file, patch_file),
+        None => Cartridge::from_path(&rom_file),
+    };
+    let cart = match cart {
+        Ok(cart) => cart,
+        Err(e) => {
+            error!("can't load the rom: {}", e);
+            return;
+        },
+    };
     let mut device = Device::new(screen);
     info!("device created");
     device.insert_cartridge(cart);
+    if let Some(wav_file) = wav_file {
+        match WavSink::create(&wav_file, sample_rate) {
+            Ok(sink) => device.set_audio_sink(Box::new(sink)),
+            Err(e) => error!("can't create wav file {}: {}", wav_file, e),
+        }
+    }
     
     for table in 0 .. 2 {
         for idx in 0 .. 128 * 128 {
@@ -255,6 +485,12 @@ fn main() {
             },
             ClockType::Undefined => (),
         }
+        device.update_audio();
+        if update_screen {
+            device.update_waveforms();
+            device.update_save();
+            device.update_track_display();
+        }
         device.ppu.borrow_mut().read_all_sprites(0);
         device.ppu.borrow_mut().read_all_sprites(1);
         for table in 0 .. 2 {
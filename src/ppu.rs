@@ -1,6 +1,6 @@
 use std::rc::Rc;
 use std::cell::RefCell;
-use crate::program::{Cartridge, Mirroring};
+use crate::program::{Cartridge, Nametable};
 
 struct Control {
     data: u8,
@@ -536,6 +536,9 @@ impl<'a> Ppu {
                     self.tmp_addr.set_low_address(data);
                     self.cur_addr = self.tmp_addr;
                     self.latch = !self.latch;
+                    if self.cur_addr.data & 0x3FFF < 0x3F00 {
+                        self.put_address_on_bus(self.cur_addr.data & 0x3FFF);
+                    }
                 }
                 if self.debug{
                     info!("ppu: (scroll) update tmp addr register: {:02X} ({:08b})", self.tmp_addr.data, self.tmp_addr.data);
@@ -557,33 +560,8 @@ impl<'a> Ppu {
         if address < 0x2000 {
             data = self.read_from_cartridge(address);
         } else if address >= 0x2000 && address < 0x3F00 {
-            let address = (address & 0x0FFF) as usize;
-            let mirroring = self.cartridge.as_ref().unwrap().borrow().get_mirroring();
-            match mirroring {
-                Mirroring::HORISONTAL => {
-                    if address < 0x400 {
-                        data = self.name_table[0][address];
-                    } else if address >= 0x400 && address < 0x800 {
-                        data = self.name_table[0][address & 0x3FF];
-                    } else if address >= 0x800 && address < 0xC00 {
-                        data = self.name_table[1][address & 0x3FF];
-                    } else if address >= 0xC00 && address < 0x1000 {
-                        data = self.name_table[1][address & 0x3FF];
-                    }
-                },
-                Mirroring::VERTICAL => {
-                    if address < 0x400 {
-                        data = self.name_table[0][address];
-                    } else if address >= 0x400 && address < 0x800 {
-                        data = self.name_table[1][address & 0x3FF];
-                    } else if address >= 0x800 && address < 0xC00 {
-                        data = self.name_table[0][address & 0x3FF];
-                    } else if address >= 0xC00 && address < 0x1000 {
-                        data = self.name_table[1][address & 0x3FF];
-                    }
-                },
-                _ => (),
-            }
+            self.put_address_on_bus(address);
+            data = self.read_nametable(address);
         } else if address >= 0x3F00 && address < 0x3FFF {
             let mut address = (address & 0x001F) as usize;
             match address {
@@ -605,35 +583,11 @@ impl<'a> Ppu {
     pub fn write_ppu(&mut self, address: u16, data: u8) {
         let address = address & 0x3FFF;
         if address < 0x2000 {
+            self.put_address_on_bus(address);
             self.cartridge.as_mut().unwrap().as_ref().borrow_mut().write_chr_rom(address, data);
         } else if address >= 0x2000 && address < 0x3F00 {
-            let address = (address & 0x0FFF) as usize;
-            let mirroring = self.cartridge.as_ref().unwrap().borrow().get_mirroring();
-            match mirroring {
-                Mirroring::HORISONTAL => {
-                    if address < 0x400 {
-                        self.name_table[0][address] = data;
-                    } else if address >= 0x400 && address < 0x800 {
-                        self.name_table[0][address & 0x3FF] = data;
-                    } else if address >= 0x800 && address < 0xC00 {
-                        self.name_table[1][address & 0x3FF] = data;
-                    } else if address >= 0xC00 && address < 0x1000 {
-                        self.name_table[1][address & 0x3FF] = data;
-                    }
-                },
-                Mirroring::VERTICAL => {
-                    if address < 0x400 {
-                        self.name_table[0][address] = data;
-                    } else if address >= 0x400 && address < 0x800 {
-                        self.name_table[1][address & 0x3FF] = data;
-                    } else if address >= 0x800 && address < 0xC00 {
-                        self.name_table[0][address & 0x3FF] = data;
-                    } else if address >= 0xC00 && address < 0x1000 {
-                        self.name_table[1][address & 0x3FF] = data;
-                    }
-                },
-                _ => (),
-            }
+            self.put_address_on_bus(address);
+            self.write_nametable(address, data);
         } else if address >= 0x3F00 && address < 0x3FFF {
             let address = address & 0x001F;
             match address {
@@ -647,7 +601,39 @@ impl<'a> Ppu {
         }
     }
 
+    fn read_nametable(&self, address: u16) -> u8 {
+        let cartridge = self.cartridge.as_ref().unwrap().borrow();
+        match cartridge.nametable_addr(address) {
+            Nametable::Ciram(offset) => self.name_table[offset >> 10][offset & 0x3FF],
+            _ => cartridge.read_nametable(address),
+        }
+    }
+
+    fn write_nametable(&mut self, address: u16, data: u8) {
+        let mut cartridge = self.cartridge.as_ref().unwrap().borrow_mut();
+        match cartridge.nametable_addr(address) {
+            Nametable::Ciram(offset) => self.name_table[offset >> 10][offset & 0x3FF] = data,
+            _ => cartridge.write_nametable(address, data),
+        }
+    }
+
     fn read_from_cartridge(&self, address: u16) -> u8 {
+        let mut data = 0;
+        self.put_address_on_bus(address);
+        let mut cartridge = self.cartridge.as_ref().unwrap().borrow_mut();
+        cartridge.read_chr_rom(address, &mut data);
+        // MMC2 / MMC4 latches switch after the fetch of the tile
+        cartridge.ppu_read(address);
+        data
+    }
+
+    // lets the mapper observe the ppu bus
+    fn put_address_on_bus(&self, address: u16) {
+        self.cartridge.as_ref().unwrap().borrow_mut().ppu_address(address);
+    }
+
+    // reading without side effects on the mapper, used for debug views
+    fn peek_from_cartridge(&self, address: u16) -> u8 {
         let mut data = 0;
         self.cartridge.as_ref().unwrap().borrow().read_chr_rom(address, &mut data);
         data
@@ -681,8 +667,8 @@ impl<'a> Ppu {
             for x in 0..16 {
                 let offset = y * 256 + x * 16;
                 for row in 0..8 {
-                    let mut low = self.read_from_cartridge(start + (offset + row) as u16);
-                    let mut high = self.read_from_cartridge(start + (offset + row) as u16 + 8);
+                    let mut low = self.peek_from_cartridge(start + (offset + row) as u16);
+                    let mut high = self.peek_from_cartridge(start + (offset + row) as u16 + 8);
                     for column in 0..8 {
                         let pixel = ((high & 0x01) << 1) | (low & 0x01);
                         low >>= 1;
@@ -735,26 +721,35 @@ impl<'a> Ppu {
         (idx4 << 3) | (idx3  << 2) | (idx2 << 1) | idx1
     }
 
+    // the pattern fetches are seen by the mapper, the ppu doesn't make them while rendering is disabled
+    fn rendering_enabled(&self) -> bool {
+        self.mask.background_enable() || self.mask.sprites_enable()
+    }
+
+    // 8x16 sprites take the pattern table from bit 0 of the tile id, the bottom half is the next tile
+    fn sprite_pattern_address(&self, id: u8, row: u16) -> u16 {
+        let id = id as u16;
+        match self.control.sprite_size() {
+            16 => (id & 0x01) * 0x1000 + (id & 0xFE) * 16 + (row & 0x07) + (row & 0x08) * 2,
+            _  => self.control.sprite_table_address() + id * 16 + row,
+        }
+    }
+
     fn update_sprite_shift_registers(&mut self) {
         for i in 0..8 {
             let oam = self.oam_buffer[i];
             let y_offset = self.skanline.overflowing_sub(oam.y_position as u16).0;
-            if y_offset < self.control.sprite_size() as u16 {
-                let sprite_id = oam.id as u16;
-
+            if y_offset < self.control.sprite_size() as u16 && self.rendering_enabled() {
                 let offset = match oam.vertical_flip() {
                     true  => self.control.sprite_size() as u16 - y_offset - 1,
                     false => y_offset
                 };
 
-                let pattern_low_byte_address = self.control.sprite_table_address() + sprite_id * 16 + offset;
-                let pattern_high_byte_address = self.control.sprite_table_address() + sprite_id * 16 + 8 + offset;
+                let pattern_low_byte_address = self.sprite_pattern_address(oam.id, offset);
+                let pattern_high_byte_address = pattern_low_byte_address + 8;
 
-                let mut low_byte = 0;
-                let mut high_byte = 0;
-
-                self.cartridge.as_ref().unwrap().borrow().read_chr_rom(pattern_low_byte_address, &mut low_byte);
-                self.cartridge.as_ref().unwrap().borrow().read_chr_rom(pattern_high_byte_address, &mut high_byte);
+                let mut low_byte = self.read_from_cartridge(pattern_low_byte_address);
+                let mut high_byte = self.read_from_cartridge(pattern_high_byte_address);
 
                 // pixel bit reading by 0x01 mask, but first bit to read placed at 0x80 position,
                 // that means we should reverse bits for normal rendering of sprite
@@ -769,10 +764,25 @@ impl<'a> Ppu {
                 self.sprite_high_shift_register[i] = high_byte;
                 self.sprite_attribute_shift_register[i] = attribute;
                 self.sprite_priority_shift_register[i] = priority;
+            } else {
+                self.dummy_sprite_fetch();
             }
         }
     }
 
+    // unused sprite slots still fetch tile $FF, mappers watching the ppu bus rely on it
+    fn dummy_sprite_fetch(&self) {
+        if !self.rendering_enabled() {
+            return;
+        }
+        let address = match self.control.sprite_size() {
+            16 => 0x1FF0,
+            _  => self.control.sprite_table_address() + 0x0FF0,
+        };
+        self.read_from_cartridge(address);
+        self.read_from_cartridge(address + 8);
+    }
+
     fn pop_sprite_pixel_with_priority(&mut self, bg_pixel: u16) -> u16 {
         let mut bit0;
         let mut bit1;
@@ -961,6 +971,10 @@ impl<'a> Ppu {
             self.update_sprite_shift_registers();
         }
 
+        if self.cycle == 257 && self.skanline == 261 {
+            (0..8).for_each(|_| self.dummy_sprite_fetch());
+        }
+
         self.cycle += 1;
         if self.cycle > 340 {
             self.cycle = 0;
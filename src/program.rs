@@ -1,105 +1,554 @@
-use std::fs::File;
-use std::io::prelude::*;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
 
 mod mapper;
+mod header;
+mod error;
+mod archive;
+mod checksum;
+mod patch;
+mod database;
+mod unif;
+mod fds;
+mod nsf;
 
+pub use header::{RomHeader, ConsoleType, Timing};
+pub use error::CartridgeError;
+pub use patch::{apply_patch, create_ips, PatchError};
+pub use checksum::{crc32, sha1};
+pub use database::{GameDatabase, GameInfo};
+pub use nsf::NsfHeader;
 use mapper::Mapper;
+pub use mapper::Nametable;
 use mapper::mapper000::Mapper000;
 use mapper::mapper001::Mapper001;
+use mapper::mapper002::Mapper002;
+use mapper::mapper003::Mapper003;
+use mapper::mapper004::Mapper004;
+use mapper::mapper005::Mapper005;
+use mapper::mapper007::Mapper007;
+use mapper::mapper009::Mapper009;
+use mapper::mapper010::Mapper010;
+use mapper::mapper019::Mapper019;
+use mapper::mapper020::Mapper020;
+use mapper::mapper021::Mapper021;
+use mapper::mapper024::Mapper024;
+use mapper::mapper069::Mapper069;
+use mapper::mapper085::Mapper085;
+use mapper::nsf::NsfMapper;
 
 const PRG_BLOCK_SIZE: usize = 16384;
 const CHR_BLOCK_SIZE: usize = 8192;
+const FOUR_SCREEN_VRAM_SIZE: usize = 2048;
+const TRAINER_SIZE: usize = 512;
+const TRAINER_OFFSET: usize = 0x1000; // 0x7000 in prg ram
+const MMC5_PRG_RAM_SIZE: usize = 65536;
 
-#[derive(Clone, Copy)]
+#[derive(Debug, Clone, Copy)]
 pub enum Mirroring {
     HORISONTAL,
     VERTICAL,
+    SINGLE_SCREEN_LOW,
+    SINGLE_SCREEN_HIGH,
+    FOUR_SCREEN,
     UNDEFINED,
 }
 
-pub struct Cartridge {
+// header and rom data of a parsed file
+struct RomImage {
+    header: RomHeader,
     prg_rom: Vec<u8>,
     chr_rom: Vec<u8>,
+    trainer: Option<Vec<u8>>,
+    title: Option<String>,
+}
+
+pub struct Cartridge {
+    prg_rom: Vec<u8>,
+    // pattern tables, chr ram is used when the rom has no chr banks
+    chr_memory: Vec<u8>,
+    chr_is_ram: bool,
+    // additional nametables for four-screen boards (0x2800 - 0x2FFF)
+    vram: Vec<u8>,
+    four_screen: bool,
+    // work ram at 0x6000 - 0x7FFF, kept in the save file when battery-backed
+    prg_ram: Vec<u8>,
+    battery: bool,
+    save_file: Option<PathBuf>,
+    prg_ram_changed: bool,
+    // 512 bytes placed at 0x7000 - 0x71FF before the start
+    trainer: Option<Vec<u8>>,
     mapper: Box<dyn Mapper>,
+    header: RomHeader,
+    game: Option<GameInfo>,
+    nsf: Option<NsfHeader>,
 }
 
 impl Cartridge {
-    pub fn new(file_name: &str) -> Cartridge {
-        let mut file = File::open(file_name).unwrap();
-        let mut memory: Vec<u8> = Vec::new();
-        file.read_to_end(&mut memory).unwrap();
-
-        let header = &memory[..16];
-
-        let prg_amount = memory[4] as usize;
-        let chr_amount = memory[5] as usize;
-        let prg_size = prg_amount * PRG_BLOCK_SIZE;
-        let chr_size = if chr_amount != 0 {
-            chr_amount * CHR_BLOCK_SIZE
-        } else {
-            CHR_BLOCK_SIZE
+    // loads a .nes file (or a zip / gzip archive with it), battery-backed ram is kept in a .sav file next to it,
+    // an .ips / .bps / .ups patch with the same name is applied automatically
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Cartridge, CartridgeError> {
+        let path = path.as_ref();
+        Cartridge::load(path, patch::find_patch(path).as_deref())
+    }
+
+    pub fn from_path_with_patch<P: AsRef<Path>, Q: AsRef<Path>>(path: P, patch_path: Q) -> Result<Cartridge, CartridgeError> {
+        Cartridge::load(path.as_ref(), Some(patch_path.as_ref()))
+    }
+
+    fn load(path: &Path, patch_path: Option<&Path>) -> Result<Cartridge, CartridgeError> {
+        let data = fs::read(path)?;
+        let mut data = archive::extract_rom(&data)?.into_owned();
+        if let Some(patch_path) = patch_path {
+            data = patch::apply_patch(&data, &fs::read(patch_path)?)?;
+            info!("patch applied: {}", patch_path.display());
+        }
+        let mut cartridge = match fds::is_disk_image(&data) {
+            true => {
+                let bios_path = fds::find_bios(path).ok_or(CartridgeError::MissingBios)?;
+                Cartridge::from_fds_bytes(&data, &fs::read(bios_path)?)?
+            },
+            false => Cartridge::from_bytes_with_database(&data, GameDatabase::default_database())?,
         };
-        info!("size_prg: {} | size_chr: {}", prg_amount, chr_amount);
-        
+        cartridge.set_save_file(path.with_extension("sav"));
+        Ok(cartridge)
+    }
 
-        let trainer = (memory[6] & 0x04) != 0;
-        let mut idx = 16;
-        if trainer {
-            idx += 512;
+    // uses only the built-in database, nothing is read from the disk
+    pub fn from_bytes(data: &[u8]) -> Result<Cartridge, CartridgeError> {
+        Cartridge::from_bytes_with_database(data, GameDatabase::builtin())
+    }
+
+    // ines, nes 2.0 and unif formats are supported,
+    // header fields are replaced by the database entry of the game if it is found
+    pub fn from_bytes_with_database(data: &[u8], database: &GameDatabase) -> Result<Cartridge, CartridgeError> {
+        let data = archive::extract_rom(data)?;
+        if fds::is_disk_image(&data) {
+            return Err(CartridgeError::MissingBios);
+        }
+        if nsf::is_nsf(&data) {
+            return Cartridge::from_nsf_bytes(&data);
         }
+        let RomImage { mut header, prg_rom, chr_rom, trainer, title } = match data.starts_with(unif::SIGNATURE) {
+            true  => unif::parse(&data)?,
+            false => Cartridge::parse_ines(&data)?,
+        };
 
-        let mut prg_rom: Vec<u8> = vec![0; prg_size];
-        let mut chr_rom: Vec<u8> = vec![0; chr_size];
-        prg_rom.clone_from_slice(&memory[idx .. idx + prg_size]);
-        idx += prg_size;
-        if chr_amount != 0 {
-            chr_rom.clone_from_slice(&memory[idx .. idx + chr_size]);
+        let rom: Vec<u8> = prg_rom.iter().chain(chr_rom.iter()).copied().collect();
+        let game = match database.find(&rom) {
+            Some(game) => {
+                info!("game found in database: {}", game.title.as_deref().unwrap_or("unknown"));
+                game.apply(&mut header);
+                Some(game.clone())
+            },
+            None => title.map(|title| GameInfo { title: Some(title), ..Default::default() }),
+        };
+        info!("{:?}", header);
+        let mapper = Cartridge::create_mapper(&header)?;
+        Ok(Cartridge::build(header, prg_rom, chr_rom, trainer, game, mapper))
+    }
+
+    // famicom disk system image (.fds or QD), the bios is the 8kb disksys.rom
+    pub fn from_fds_bytes(data: &[u8], bios: &[u8]) -> Result<Cartridge, CartridgeError> {
+        let data = archive::extract_rom(data)?;
+        let sides = fds::parse_sides(&data)?;
+        if bios.len() < fds::BIOS_SIZE {
+            return Err(CartridgeError::Truncated { expected: fds::BIOS_SIZE, actual: bios.len() });
         }
-        let mapper = Cartridge::create_mapper(header, prg_amount, chr_amount);
+        let header = RomHeader {
+            nes2: false,
+            mapper: 20,
+            submapper: 0,
+            prg_rom_size: fds::BIOS_SIZE,
+            chr_rom_size: 0,
+            prg_ram_size: 32768,
+            prg_nvram_size: 0,
+            chr_ram_size: CHR_BLOCK_SIZE,
+            chr_nvram_size: 0,
+            vertical_mirroring: false,
+            four_screen: false,
+            battery: false,
+            trainer: false,
+            console_type: ConsoleType::Nes,
+            timing: Timing::Ntsc,
+            misc_roms: 0,
+            expansion_device: 0,
+        };
+        let prg_rom = bios[bios.len() - fds::BIOS_SIZE..].to_vec();
+        let mapper = Box::new(Mapper020::new(sides));
+        Ok(Cartridge::build(header, prg_rom, Vec::new(), None, None, mapper))
+    }
+
+    // nsf tune, played by the driver of the nsf mapper
+    pub fn from_nsf_bytes(data: &[u8]) -> Result<Cartridge, CartridgeError> {
+        let data = archive::extract_rom(data)?;
+        let (nsf, program) = nsf::parse(&data)?;
+        let header = RomHeader {
+            nes2: false,
+            mapper: 0,
+            submapper: 0,
+            prg_rom_size: program.len(),
+            chr_rom_size: 0,
+            prg_ram_size: 0,
+            prg_nvram_size: 0,
+            chr_ram_size: CHR_BLOCK_SIZE,
+            chr_nvram_size: 0,
+            vertical_mirroring: true,
+            four_screen: false,
+            battery: false,
+            trainer: false,
+            console_type: ConsoleType::Nes,
+            timing: if nsf.pal { Timing::Pal } else { Timing::Ntsc },
+            misc_roms: 0,
+            expansion_device: 0,
+        };
+        let mapper = Box::new(NsfMapper::new(&nsf, &program));
+        let mut cartridge = Cartridge::build(header, program, Vec::new(), None, None, mapper);
+        cartridge.nsf = Some(nsf);
+        Ok(cartridge)
+    }
+
+    fn build(
+        header: RomHeader,
+        prg_rom: Vec<u8>,
+        chr_rom: Vec<u8>,
+        trainer: Option<Vec<u8>>,
+        game: Option<GameInfo>,
+        mapper: Box<dyn Mapper>,
+    ) -> Cartridge {
+        let chr_is_ram = chr_rom.is_empty();
+        let chr_memory = match (chr_is_ram, header.chr_ram_total()) {
+            (true, 0) => vec![0; CHR_BLOCK_SIZE],
+            (true, size) => vec![0; size],
+            (false, _) => chr_rom,
+        };
+
+        let vram = match header.four_screen {
+            true  => vec![0; FOUR_SCREEN_VRAM_SIZE],
+            false => Vec::new(),
+        };
+        let prg_ram_size = match trainer.is_some() {
+            true  => header.prg_ram_total().max(TRAINER_OFFSET + TRAINER_SIZE),
+            false => header.prg_ram_total(),
+        };
+        // iNES 1.0 can't tell the prg ram size of MMC5 boards, the largest one has 64kb
+        let prg_ram_size = match header.mapper == 5 && !header.nes2 {
+            true  => prg_ram_size.max(MMC5_PRG_RAM_SIZE),
+            false => prg_ram_size,
+        };
 
-        Cartridge {
+        let mut cartridge = Cartridge {
             prg_rom,
-            chr_rom,
-            mapper
+            chr_memory,
+            chr_is_ram,
+            vram,
+            four_screen: header.four_screen,
+            prg_ram: vec![0; prg_ram_size],
+            battery: header.battery,
+            save_file: None,
+            prg_ram_changed: false,
+            trainer,
+            mapper,
+            header,
+            game,
+            nsf: None,
+        };
+        cartridge.load_trainer();
+        cartridge
+    }
+
+    fn parse_ines(data: &[u8]) -> Result<RomImage, CartridgeError> {
+        let header = RomHeader::parse(data).ok_or(CartridgeError::BadMagic)?;
+        let prg_size = header.prg_rom_size;
+        if prg_size == 0 {
+            return Err(CartridgeError::NoPrgRom);
+        }
+
+        let mut idx = RomHeader::SIZE;
+        if header.trainer {
+            idx += TRAINER_SIZE;
+        }
+        // sizes in the exponent notation can be far beyond any file (saturated to usize::MAX)
+        let expected = idx.checked_add(prg_size)
+            .and_then(|size| size.checked_add(header.chr_rom_size))
+            .unwrap_or(usize::MAX);
+        if data.len() < expected {
+            return Err(CartridgeError::Truncated { expected, actual: data.len() });
+        }
+
+        let trainer = match header.trainer {
+            true  => Some(data[RomHeader::SIZE .. RomHeader::SIZE + TRAINER_SIZE].to_vec()),
+            false => None,
+        };
+        let prg_rom = data[idx .. idx + prg_size].to_vec();
+        let chr_rom = data[idx + prg_size .. expected].to_vec();
+        Ok(RomImage { header, prg_rom, chr_rom, trainer, title: None })
+    }
+
+    // battery-backed ram (or the disk changes of the disk system) is loaded from the file if it exists
+    // and saved to it later
+    pub fn set_save_file<P: AsRef<Path>>(&mut self, save_file: P) {
+        let save_file = save_file.as_ref().to_path_buf();
+        if self.battery {
+            Cartridge::load_prg_ram(&save_file, &mut self.prg_ram);
+            self.load_trainer();
+        } else if save_file.is_file() {
+            match fs::read(&save_file) {
+                Ok(data) => if let Some(drive) = self.disk_drive_mut() {
+                    drive.load_save_data(&data);
+                },
+                Err(e) => error!("can't load {}: {}", save_file.display(), e),
+            }
+        }
+        self.save_file = Some(save_file);
+    }
+
+    fn load_trainer(&mut self) {
+        if let Some(trainer) = self.trainer.as_ref() {
+            self.prg_ram[TRAINER_OFFSET .. TRAINER_OFFSET + TRAINER_SIZE].copy_from_slice(trainer);
+            info!("trainer loaded at 0x7000");
+        }
+    }
+
+    pub fn header(&self) -> &RomHeader {
+        &self.header
+    }
+
+    // database entry of the game, if it is known
+    pub fn game_info(&self) -> Option<&GameInfo> {
+        self.game.as_ref()
+    }
+
+    pub fn title(&self) -> Option<&str> {
+        match self.nsf.as_ref() {
+            Some(nsf) => Some(nsf.name.as_str()),
+            None => self.game.as_ref().and_then(|game| game.title.as_deref()),
+        }
+    }
+
+    pub fn nsf_header(&self) -> Option<&NsfHeader> {
+        self.nsf.as_ref()
+    }
+
+    fn load_prg_ram(save_file: &Path, prg_ram: &mut [u8]) {
+        match fs::read(save_file) {
+            Ok(data) => {
+                let size = data.len().min(prg_ram.len());
+                prg_ram[..size].copy_from_slice(&data[..size]);
+                info!("prg ram loaded from {}", save_file.display());
+            },
+            Err(e) if e.kind() == io::ErrorKind::NotFound => (),
+            Err(e) => error!("can't load {}: {}", save_file.display(), e),
+        }
+    }
+
+    // writes battery-backed prg ram (or the disk changes) to the save file if it was changed since the last save
+    pub fn save(&mut self) -> io::Result<()> {
+        let save_file = match self.save_file.as_ref() {
+            Some(save_file) => save_file,
+            None => return Ok(()),
+        };
+        if let Some(drive) = self.mapper.as_mut().as_any_mut().downcast_mut::<Mapper020>() {
+            if let Some(data) = drive.save_data() {
+                fs::write(save_file, data)?;
+                drive.save_data_written();
+                info!("disk changes saved to {}", save_file.display());
+            }
         }
+        if self.battery && self.prg_ram_changed {
+            fs::write(save_file, &self.prg_ram)?;
+            self.prg_ram_changed = false;
+            info!("prg ram saved to {}", save_file.display());
+        }
+        Ok(())
     }
 
-    fn create_mapper(header: &[u8], prg_amount: usize, chr_amount: usize) -> Box<dyn Mapper> {
-        let low = (header[6] & 0xF0) >> 4;
-        let high = header[7] & 0xF0;
-        let mapper_id = high | low;
-        let mapper: Box<dyn Mapper> = match mapper_id {
-            000 => Box::new(Mapper000::new(prg_amount, header[6] & 0x1)),
+    fn create_mapper(header: &RomHeader) -> Result<Box<dyn Mapper>, CartridgeError> {
+        // prg smaller than 16kb (NES 2.0 exponent notation, UNIF) still takes a whole bank, reads wrap around
+        let prg_amount = header.prg_rom_size.div_ceil(PRG_BLOCK_SIZE);
+        let chr_amount = header.chr_rom_size / CHR_BLOCK_SIZE;
+        let mirroring_bit = header.vertical_mirroring as u8;
+        // submapper 1 of discrete boards means no bus conflicts, 2 means bus conflicts
+        let bus_conflicts = |default: bool| match header.submapper {
+            1 => false,
+            2 => true,
+            _ => default,
+        };
+        let mapper: Box<dyn Mapper> = match header.mapper {
+            000 => Box::new(Mapper000::new(prg_amount, mirroring_bit)),
             001 => Box::new(Mapper001::new(prg_amount, chr_amount)),
-            _   => panic!("unknown mapper: {}", mapper_id),
+            002 => Box::new(Mapper002::new(prg_amount, mirroring_bit, bus_conflicts(true))),
+            003 => Box::new(Mapper003::new(prg_amount, chr_amount, mirroring_bit, bus_conflicts(true))),
+            004 => Box::new(Mapper004::new(prg_amount, chr_amount, mirroring_bit)),
+            005 => Box::new(Mapper005::new(prg_amount)),
+            007 => Box::new(Mapper007::new(prg_amount, bus_conflicts(false))),
+            009 => Box::new(Mapper009::new(prg_amount, chr_amount)),
+            010 => Box::new(Mapper010::new(prg_amount, chr_amount)),
+            019 => Box::new(Mapper019::new(prg_amount)),
+            021 | 022 | 023 | 025 => Box::new(Mapper021::new(prg_amount, header.mapper, header.submapper)),
+            024 => Box::new(Mapper024::new(prg_amount, false)),
+            026 => Box::new(Mapper024::new(prg_amount, true)),
+            069 => Box::new(Mapper069::new(prg_amount)),
+            085 => Box::new(Mapper085::new(prg_amount, header.submapper)),
+            _   => return Err(CartridgeError::UnsupportedMapper(header.mapper)),
         };
-        mapper
+        Ok(mapper)
     }
 
     pub fn get_mirroring(&self) -> Mirroring {
-        self.mapper.as_ref().mirroring()
+        match self.four_screen {
+            true  => Mirroring::FOUR_SCREEN,
+            false => self.mapper.as_ref().mirroring(),
+        }
+    }
+
+    pub fn nametable_addr(&self, address: u16) -> Nametable {
+        match self.four_screen {
+            true  => mapper::mirrored_nametable(Mirroring::FOUR_SCREEN, address),
+            false => self.mapper.nametable_addr(address),
+        }
     }
 
-    pub fn read_prg_rom(&self, address: u16, data: &mut u8) {
+    // nametables which are not placed in the console memory
+    pub fn read_nametable(&self, address: u16) -> u8 {
+        match self.nametable_addr(address) {
+            Nametable::Vram(offset) => self.vram[offset],
+            Nametable::Chr(offset) => self.chr_memory[offset % self.chr_memory.len()],
+            Nametable::Mapper => self.mapper.read_nametable(address),
+            Nametable::Ciram(_) | Nametable::Unmapped => 0,
+        }
+    }
+
+    pub fn write_nametable(&mut self, address: u16, data: u8) {
+        match self.nametable_addr(address) {
+            Nametable::Vram(offset) => self.vram[offset] = data,
+            Nametable::Mapper => self.mapper.write_nametable(address, data),
+            Nametable::Ciram(_) | Nametable::Chr(_) | Nametable::Unmapped => (),
+        }
+    }
+
+    pub fn irq_pending(&self) -> bool {
+        self.mapper.irq_pending()
+    }
+
+    pub fn irq_acknowledge(&mut self) {
+        self.mapper.irq_acknowledge();
+    }
+
+    pub fn clock(&mut self) {
+        self.mapper.clock();
+    }
+
+    pub fn audio_output(&self) -> f32 {
+        self.mapper.audio_output()
+    }
+
+    fn disk_drive(&self) -> Option<&Mapper020> {
+        self.mapper.as_ref().as_any().downcast_ref::<Mapper020>()
+    }
+
+    fn disk_drive_mut(&mut self) -> Option<&mut Mapper020> {
+        self.mapper.as_mut().as_any_mut().downcast_mut::<Mapper020>()
+    }
+
+    fn nsf_player(&self) -> Option<&NsfMapper> {
+        self.mapper.as_ref().as_any().downcast_ref::<NsfMapper>()
+    }
+
+    fn nsf_player_mut(&mut self) -> Option<&mut NsfMapper> {
+        self.mapper.as_mut().as_any_mut().downcast_mut::<NsfMapper>()
+    }
+
+    // 0 without a disk drive
+    pub fn disk_sides(&self) -> usize {
+        self.disk_drive().map_or(0, |drive| drive.disk_sides())
+    }
+
+    pub fn disk_side(&self) -> Option<usize> {
+        self.disk_drive().and_then(|drive| drive.disk_side())
+    }
+
+    // None ejects the disk
+    pub fn insert_disk(&mut self, side: Option<usize>) {
+        if let Some(drive) = self.disk_drive_mut() {
+            drive.insert_disk(side);
+        }
+    }
+
+    // None for everything but nsf tunes
+    pub fn track(&self) -> Option<usize> {
+        self.nsf_player().map(|player| player.track())
+    }
+
+    // the track starts after a cpu reset
+    pub fn select_track(&mut self, track: usize) {
+        if let Some(player) = self.nsf_player_mut() {
+            player.select_track(track);
+        }
+    }
+
+    pub fn ppu_address(&mut self, address: u16) {
+        self.mapper.ppu_address(address);
+    }
+
+    pub fn ppu_read(&mut self, address: u16) {
+        self.mapper.ppu_read(address);
+    }
+
+    pub fn ppu_register_write(&mut self, address: u16, data: u8) {
+        self.mapper.ppu_register_write(address, data);
+    }
+
+    pub fn read_prg_rom(&mut self, address: u16, data: &mut u8) {
+        if !self.mapper.prg_read_register(address, data) {
+            self.peek_prg_rom(address, data);
+        }
+    }
+
+    // read without side effects on mapper registers
+    pub fn peek_prg_rom(&self, address: u16, data: &mut u8) {
         let mut cartridge_addr = 0;
-        if self.mapper.prg_read_addr(address, &mut cartridge_addr) {
-            *data = self.prg_rom[cartridge_addr];
+        if self.mapper.prg_ram_addr(address, &mut cartridge_addr) && !self.prg_ram.is_empty() {
+            *data = self.prg_ram[cartridge_addr % self.prg_ram.len()];
+        } else if self.mapper.prg_read_addr(address, &mut cartridge_addr) {
+            *data = self.prg_rom[cartridge_addr % self.prg_rom.len()];
         }
     }
 
     pub fn read_chr_rom(&self, address: u16, data: &mut u8) {
         let mut cartridge_addr = 0;
         if self.mapper.chr_read_addr(address, &mut cartridge_addr) {
-            *data = self.chr_rom[cartridge_addr];
+            *data = self.chr_memory[cartridge_addr % self.chr_memory.len()];
         }
     }
 
     pub fn write_prg_rom(&mut self, address: u16, data: u8) {
+        let mut cartridge_addr = 0;
+        if self.mapper.prg_ram_addr(address, &mut cartridge_addr) && self.mapper.prg_ram_writable() && !self.prg_ram.is_empty() {
+            let cartridge_addr = cartridge_addr % self.prg_ram.len();
+            if self.prg_ram[cartridge_addr] != data {
+                self.prg_ram[cartridge_addr] = data;
+                self.prg_ram_changed = true;
+            }
+        }
+        let mut data = data;
+        if address >= 0x8000 && self.mapper.bus_conflicts() {
+            let mut rom_data = data;
+            self.peek_prg_rom(address, &mut rom_data);
+            data &= rom_data;
+        }
         self.mapper.prg_write_addr(address, data);
     }
 
     pub fn write_chr_rom(&mut self, address: u16, data: u8) {
-        self.mapper.chr_write_addr(address, data);
-        self.chr_rom[address as usize] = data;
+        if !self.chr_is_ram {
+            return;
+        }
+        let mut cartridge_addr = 0;
+        if self.mapper.chr_write_addr(address, &mut cartridge_addr) {
+            let size = self.chr_memory.len();
+            self.chr_memory[cartridge_addr % size] = data;
+        }
     }
 }
@@ -0,0 +1,35 @@
+use std::borrow::Cow;
+use std::io::{Cursor, Read};
+
+use flate2::read::GzDecoder;
+use zip::ZipArchive;
+
+use super::CartridgeError;
+
+const ZIP_SIGNATURE: &[u8] = b"PK\x03\x04";
+const GZIP_SIGNATURE: &[u8] = &[0x1F, 0x8B];
+const ROM_EXTENSIONS: [&str; 5] = [".nes", ".unf", ".unif", ".fds", ".nsf"];
+
+// rom image from a zip (first .nes, .unf, .fds or .nsf entry) or gzip archive, other data is returned as is
+pub fn extract_rom(data: &[u8]) -> Result<Cow<'_, [u8]>, CartridgeError> {
+    if data.starts_with(ZIP_SIGNATURE) {
+        let mut archive = ZipArchive::new(Cursor::new(data))?;
+        for idx in 0..archive.len() {
+            let mut file = archive.by_index(idx)?;
+            let name = file.name().to_lowercase();
+            if file.is_file() && ROM_EXTENSIONS.iter().any(|extension| name.ends_with(extension)) {
+                info!("rom extracted from zip: {}", name);
+                let mut rom = Vec::with_capacity(file.size() as usize);
+                file.read_to_end(&mut rom)?;
+                return Ok(Cow::Owned(rom));
+            }
+        }
+        return Err(CartridgeError::NoRomInArchive);
+    }
+    if data.starts_with(GZIP_SIGNATURE) {
+        let mut rom = Vec::new();
+        GzDecoder::new(data).read_to_end(&mut rom)?;
+        return Ok(Cow::Owned(rom));
+    }
+    Ok(Cow::Borrowed(data))
+}
@@ -0,0 +1,262 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::OnceLock;
+
+use super::checksum::{crc32, sha1};
+use super::{ConsoleType, Mirroring, RomHeader, Timing};
+
+// games are described in the nes 2.0 xml format (nes20db.xml), the title is taken from the comment before <game>
+const BUILTIN_DATABASE: &str = include_str!("nes20db.xml");
+const EXTERNAL_DATABASE: &str = "nes20db.xml";
+
+#[derive(Debug, Clone, Default)]
+pub struct GameInfo {
+    pub title: Option<String>,
+    // hashes of prg and chr rom data without the header
+    pub crc32: Option<u32>,
+    pub sha1: Option<[u8; 20]>,
+    pub mapper: Option<u16>,
+    pub submapper: Option<u8>,
+    pub mirroring: Option<Mirroring>,
+    pub battery: Option<bool>,
+    pub prg_ram_size: Option<usize>,
+    pub prg_nvram_size: Option<usize>,
+    pub chr_ram_size: Option<usize>,
+    pub chr_nvram_size: Option<usize>,
+    pub console_type: Option<ConsoleType>,
+    pub timing: Option<Timing>,
+}
+
+impl GameInfo {
+    fn set(&mut self, element: &str, attributes: &[(&str, &str)]) {
+        let attribute = |name: &str| attributes.iter().find(|(key, _)| *key == name).map(|(_, value)| *value);
+        let number = |name: &str| attribute(name).and_then(|value| value.parse::<usize>().ok());
+        match element {
+            "rom" => {
+                self.crc32 = attribute("crc32").and_then(|value| u32::from_str_radix(value, 16).ok());
+                self.sha1 = attribute("sha1").and_then(parse_sha1);
+            },
+            "pcb" => {
+                self.mapper = number("mapper").map(|mapper| mapper as u16);
+                self.submapper = number("submapper").map(|submapper| submapper as u8);
+                self.mirroring = match attribute("mirroring") {
+                    Some("H") => Some(Mirroring::HORISONTAL),
+                    Some("V") => Some(Mirroring::VERTICAL),
+                    Some("4") => Some(Mirroring::FOUR_SCREEN),
+                    _ => None,
+                };
+                self.battery = number("battery").map(|battery| battery != 0);
+            },
+            "prgram" => self.prg_ram_size = number("size"),
+            "prgnvram" => self.prg_nvram_size = number("size"),
+            "chrram" => self.chr_ram_size = number("size"),
+            "chrnvram" => self.chr_nvram_size = number("size"),
+            "console" => {
+                self.console_type = number("type").map(|console_type| match console_type {
+                    0 => ConsoleType::Nes,
+                    1 => ConsoleType::VsSystem,
+                    2 => ConsoleType::Playchoice10,
+                    console_type => ConsoleType::Extended(console_type as u8),
+                });
+                self.timing = number("region").map(|region| match region {
+                    0 => Timing::Ntsc,
+                    1 => Timing::Pal,
+                    2 => Timing::MultiRegion,
+                    _ => Timing::Dendy,
+                });
+            },
+            _ => (),
+        }
+    }
+
+    // replaces the header fields which are known for the game
+    pub fn apply(&self, header: &mut RomHeader) {
+        if let Some(mapper) = self.mapper { header.mapper = mapper }
+        if let Some(submapper) = self.submapper { header.submapper = submapper }
+        match self.mirroring {
+            Some(Mirroring::HORISONTAL) => {
+                header.vertical_mirroring = false;
+                header.four_screen = false;
+            },
+            Some(Mirroring::VERTICAL) => {
+                header.vertical_mirroring = true;
+                header.four_screen = false;
+            },
+            Some(Mirroring::FOUR_SCREEN) => header.four_screen = true,
+            _ => (),
+        }
+        if let Some(battery) = self.battery { header.battery = battery }
+        // ram sizes are described as a whole, missing elements mean no ram of that kind
+        if self.prg_ram_size.is_some() || self.prg_nvram_size.is_some() {
+            header.prg_ram_size = self.prg_ram_size.unwrap_or(0);
+            header.prg_nvram_size = self.prg_nvram_size.unwrap_or(0);
+        }
+        if self.chr_ram_size.is_some() || self.chr_nvram_size.is_some() {
+            header.chr_ram_size = self.chr_ram_size.unwrap_or(0);
+            header.chr_nvram_size = self.chr_nvram_size.unwrap_or(0);
+        }
+        if let Some(console_type) = self.console_type { header.console_type = console_type }
+        if let Some(timing) = self.timing { header.timing = timing }
+    }
+}
+
+fn parse_sha1(value: &str) -> Option<[u8; 20]> {
+    if value.len() != 40 {
+        return None;
+    }
+    let mut digest = [0; 20];
+    for (idx, byte) in digest.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(value.get(idx * 2..idx * 2 + 2)?, 16).ok()?;
+    }
+    Some(digest)
+}
+
+// key="value" pairs of a tag
+fn parse_attributes(text: &str) -> Vec<(&str, &str)> {
+    let parts: Vec<&str> = text.split('"').collect();
+    parts.chunks(2)
+        .filter(|pair| pair.len() == 2)
+        .map(|pair| (pair[0].trim().trim_end_matches('=').trim(), pair[1]))
+        .collect()
+}
+
+pub struct GameDatabase {
+    games: Vec<GameInfo>,
+}
+
+impl GameDatabase {
+    pub fn new() -> GameDatabase {
+        GameDatabase { games: Vec::new() }
+    }
+
+    // parsed once and shared by all cartridges
+    pub fn builtin() -> &'static GameDatabase {
+        static BUILTIN: OnceLock<GameDatabase> = OnceLock::new();
+        BUILTIN.get_or_init(|| GameDatabase::parse(BUILTIN_DATABASE))
+    }
+
+    // nes20db.xml from the working directory if there is one, its entries take precedence over the built-in ones.
+    // the file is read on the first call only
+    pub fn default_database() -> &'static GameDatabase {
+        static DEFAULT: OnceLock<GameDatabase> = OnceLock::new();
+        DEFAULT.get_or_init(|| {
+            let mut database = GameDatabase::new();
+            if Path::new(EXTERNAL_DATABASE).is_file() {
+                match GameDatabase::load(EXTERNAL_DATABASE) {
+                    Ok(external) => database.extend(external),
+                    Err(e) => error!("can't load {}: {}", EXTERNAL_DATABASE, e),
+                }
+            }
+            database.extend(GameDatabase::parse(BUILTIN_DATABASE));
+            database
+        })
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<GameDatabase> {
+        Ok(GameDatabase::parse(&fs::read_to_string(path)?))
+    }
+
+    // a small reader for the flat structure of nes20db.xml, unknown elements are skipped
+    pub fn parse(xml: &str) -> GameDatabase {
+        let mut games = Vec::new();
+        let mut title = None;
+        let mut game: Option<GameInfo> = None;
+        let mut rest = xml;
+        while let Some(start) = rest.find('<') {
+            rest = &rest[start..];
+            if rest.starts_with("<!--") {
+                let end = match rest[4..].find("-->") {
+                    Some(end) => end + 4,
+                    None => break,
+                };
+                let comment = rest[4..end].trim();
+                title = Some(comment.trim_end_matches(".nes").to_string());
+                rest = &rest[end + 3..];
+                continue;
+            }
+            let end = match rest.find('>') {
+                Some(end) => end,
+                None => break,
+            };
+            let tag = rest[1..end].trim_end_matches('/');
+            rest = &rest[end + 1..];
+
+            let mut tag_parts = tag.splitn(2, char::is_whitespace);
+            let element = tag_parts.next().unwrap_or("");
+            let attributes = parse_attributes(tag_parts.next().unwrap_or(""));
+            match element {
+                "game" => game = Some(GameInfo { title: title.take(), ..Default::default() }),
+                "/game" => {
+                    if let Some(game) = game.take() {
+                        if game.crc32.is_some() || game.sha1.is_some() {
+                            games.push(game);
+                        }
+                    }
+                },
+                _ => if let Some(game) = game.as_mut() {
+                    game.set(element, &attributes);
+                },
+            }
+        }
+        GameDatabase { games }
+    }
+
+    pub fn extend(&mut self, other: GameDatabase) {
+        self.games.extend(other.games);
+    }
+
+    pub fn len(&self) -> usize {
+        self.games.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.games.is_empty()
+    }
+
+    // rom is prg and chr data without the header, sha-1 is checked when the entry has it
+    pub fn find(&self, rom: &[u8]) -> Option<&GameInfo> {
+        let crc = crc32(rom);
+        let mut digest = None;
+        self.games.iter().find(|game| {
+            if game.crc32.map_or(false, |game_crc| game_crc != crc) {
+                return false;
+            }
+            match game.sha1 {
+                Some(game_sha1) => *digest.get_or_insert_with(|| sha1(rom)) == game_sha1,
+                None => game.crc32.is_some(),
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builtin_database() {
+        assert_eq!(GameDatabase::builtin().len(), 6);
+    }
+
+    #[test]
+    fn find_by_checksum() {
+        let rom = [0x4E, 0x45, 0x53];
+        let xml = format!(
+            "<!-- Test (World).nes -->\n<game>\n<rom crc32=\"{:08X}\"/>\n<pcb mapper=\"4\" mirroring=\"4\" battery=\"1\"/>\n<prgnvram size=\"8192\"/>\n</game>",
+            crc32(&rom),
+        );
+        let database = GameDatabase::parse(&xml);
+        let game = database.find(&rom).unwrap();
+        assert_eq!(game.title.as_deref(), Some("Test (World)"));
+
+        let mut header = RomHeader::parse(b"NES\x1A\x01\x01\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00").unwrap();
+        game.apply(&mut header);
+        assert_eq!(header.mapper, 4);
+        assert!(header.four_screen);
+        assert!(header.battery);
+        assert_eq!(header.prg_nvram_size, 8192);
+        assert_eq!(header.prg_ram_size, 0);
+        assert!(database.find(&rom[1..]).is_none());
+    }
+}
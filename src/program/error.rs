@@ -0,0 +1,72 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+use zip::result::ZipError;
+
+use super::PatchError;
+
+#[derive(Debug)]
+pub enum CartridgeError {
+    BadMagic,
+    Truncated { expected: usize, actual: usize },
+    NoPrgRom,
+    UnsupportedMapper(u16),
+    UnsupportedBoard(String),
+    MissingBios,
+    NoRomInArchive,
+    Zip(ZipError),
+    Patch(PatchError),
+    Io(io::Error),
+}
+
+impl fmt::Display for CartridgeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CartridgeError::BadMagic => write!(f, "not a nes file (wrong header signature)"),
+            CartridgeError::Truncated { expected, actual } => {
+                write!(f, "rom is truncated: expected {} bytes, got {}", expected, actual)
+            },
+            CartridgeError::NoPrgRom => write!(f, "rom has no prg data"),
+            CartridgeError::UnsupportedMapper(mapper) => write!(f, "unsupported mapper: {:03}", mapper),
+            CartridgeError::UnsupportedBoard(board) => write!(f, "unsupported unif board: {}", board),
+            CartridgeError::MissingBios => write!(f, "disk system bios (disksys.rom) is required"),
+            CartridgeError::NoRomInArchive => write!(f, "archive has no .nes file"),
+            CartridgeError::Zip(e) => write!(f, "zip error: {}", e),
+            CartridgeError::Patch(e) => write!(f, "can't apply patch: {}", e),
+            CartridgeError::Io(e) => write!(f, "io error: {}", e),
+        }
+    }
+}
+
+impl Error for CartridgeError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            CartridgeError::Zip(e) => Some(e),
+            CartridgeError::Patch(e) => Some(e),
+            CartridgeError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for CartridgeError {
+    fn from(e: io::Error) -> Self {
+        CartridgeError::Io(e)
+    }
+}
+
+impl From<ZipError> for CartridgeError {
+    fn from(e: ZipError) -> Self {
+        match e {
+            ZipError::Io(e) => CartridgeError::Io(e),
+            e => CartridgeError::Zip(e),
+        }
+    }
+}
+
+impl From<PatchError> for CartridgeError {
+    fn from(e: PatchError) -> Self {
+        CartridgeError::Patch(e)
+    }
+}
@@ -0,0 +1,101 @@
+use std::path::{Path, PathBuf};
+
+use super::CartridgeError;
+
+// fwNES header: "FDS<EOF>", amount of sides and 11 unused bytes
+const HEADER_SIGNATURE: &[u8] = b"FDS\x1A";
+const HEADER_SIZE: usize = 16;
+const DISK_SIGNATURE: &[u8] = b"\x01*NINTENDO-HVC*";
+const SIDE_SIZE: usize = 65500;
+// images in the QD format keep the crc of every block
+const QD_SIDE_SIZE: usize = 65536;
+
+pub const BIOS_SIZE: usize = 8192;
+const BIOS_FILE: &str = "disksys.rom";
+
+// gaps between the blocks on the disk, in bytes
+const LEADING_GAP: usize = 28300 / 8;
+const BLOCK_GAP: usize = 976 / 8;
+
+pub fn is_disk_image(data: &[u8]) -> bool {
+    data.starts_with(HEADER_SIGNATURE) || data.starts_with(DISK_SIGNATURE)
+}
+
+// disksys.rom next to the image or in the working directory
+pub fn find_bios(image_path: &Path) -> Option<PathBuf> {
+    let next_to_image = image_path.parent().map(|dir| dir.join(BIOS_FILE));
+    next_to_image.into_iter()
+        .chain(std::iter::once(PathBuf::from(BIOS_FILE)))
+        .find(|path| path.is_file())
+}
+
+// disk sides as they pass the head of the drive: gaps, start marks, blocks and their crc
+pub fn parse_sides(data: &[u8]) -> Result<Vec<Vec<u8>>, CartridgeError> {
+    let data = match data.starts_with(HEADER_SIGNATURE) {
+        true  => data.get(HEADER_SIZE..).unwrap_or(&[]),
+        false => data,
+    };
+    let qd = data.len() % QD_SIDE_SIZE == 0 && data.len() % SIDE_SIZE != 0;
+    let side_size = if qd { QD_SIDE_SIZE } else { SIDE_SIZE };
+    let sides: Vec<Vec<u8>> = data.chunks(side_size)
+        .filter(|side| side.starts_with(DISK_SIGNATURE))
+        .map(|side| add_gaps(side, qd))
+        .collect();
+    if sides.is_empty() {
+        return Err(CartridgeError::BadMagic);
+    }
+    info!("fds: {} disk sides", sides.len());
+    Ok(sides)
+}
+
+// blocks: 1 - disk info (56 bytes), 2 - file amount (2), 3 - file header (16), 4 - file data (1 + size from the header)
+fn add_gaps(side: &[u8], with_crc: bool) -> Vec<u8> {
+    let mut output = vec![0; LEADING_GAP];
+    let mut position = 0;
+    let mut file_size = 0;
+    while position < side.len() {
+        let block_size = match side[position] {
+            1 => 56,
+            2 => 2,
+            3 => {
+                let header = side.get(position + 13..position + 15).unwrap_or(&[0, 0]);
+                file_size = header[0] as usize | (header[1] as usize) << 8;
+                16
+            },
+            4 => 1 + file_size,
+            _ => break,
+        };
+        let block = match side.get(position..position + block_size) {
+            Some(block) => block,
+            None => break,
+        };
+        output.push(0x80); // start mark
+        output.extend_from_slice(block);
+        output.extend_from_slice(&block_crc(block).to_le_bytes());
+        output.extend(std::iter::repeat(0).take(BLOCK_GAP));
+        position += block_size;
+        if with_crc {
+            position += 2;
+        }
+    }
+    output.resize(output.len().max(SIDE_SIZE + LEADING_GAP), 0);
+    output
+}
+
+// crc of a block as the drive computes it, the start mark is included
+fn block_crc(block: &[u8]) -> u16 {
+    let mut crc: u16 = 0;
+    for byte in std::iter::once(&0x80).chain(block.iter()).chain([0, 0].iter()) {
+        for bit in 0..8 {
+            let carry = crc & 0x01 != 0;
+            crc >>= 1;
+            if carry {
+                crc ^= 0x8408;
+            }
+            if byte & (1 << bit) != 0 {
+                crc ^= 0x8000;
+            }
+        }
+    }
+    crc
+}
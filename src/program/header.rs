@@ -0,0 +1,227 @@
+const PRG_ROM_UNIT: usize = 16384;
+const CHR_ROM_UNIT: usize = 8192;
+const INES_RAM_SIZE: usize = 8192;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ConsoleType {
+    Nes,
+    VsSystem,
+    Playchoice10,
+    Extended(u8), // nes 2.0 byte 13
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Timing {
+    Ntsc,
+    Pal,
+    MultiRegion,
+    Dendy,
+}
+
+// header of the .nes file, iNES 1.0 and NES 2.0 formats, all sizes are in bytes
+#[derive(Debug, Clone)]
+pub struct RomHeader {
+    pub nes2: bool,
+    pub mapper: u16,
+    pub submapper: u8,
+    pub prg_rom_size: usize,
+    pub chr_rom_size: usize,
+    pub prg_ram_size: usize,
+    pub prg_nvram_size: usize,
+    pub chr_ram_size: usize,
+    pub chr_nvram_size: usize,
+    pub vertical_mirroring: bool,
+    pub four_screen: bool,
+    pub battery: bool,
+    pub trainer: bool,
+    pub console_type: ConsoleType,
+    pub timing: Timing,
+    pub misc_roms: u8,
+    pub expansion_device: u8,
+}
+
+impl RomHeader {
+    pub const SIZE: usize = 16;
+
+    // None when the data doesn't start with the "NES<EOF>" signature
+    pub fn parse(data: &[u8]) -> Option<RomHeader> {
+        if data.len() < RomHeader::SIZE || &data[..4] != b"NES\x1A" {
+            return None;
+        }
+        let header = &data[..RomHeader::SIZE];
+        let nes2 = header[7] & 0x0C == 0x08;
+        let vertical_mirroring = header[6] & 0x01 != 0;
+        let battery = header[6] & 0x02 != 0;
+        let trainer = header[6] & 0x04 != 0;
+        let four_screen = header[6] & 0x08 != 0;
+        let console_type = match header[7] & 0x03 {
+            0 => ConsoleType::Nes,
+            1 => ConsoleType::VsSystem,
+            2 => ConsoleType::Playchoice10,
+            _ => ConsoleType::Extended(header[13] & 0x0F),
+        };
+
+        let header = match nes2 {
+            true => RomHeader {
+                nes2,
+                mapper: ((header[8] & 0x0F) as u16) << 8 | (header[7] & 0xF0) as u16 | (header[6] >> 4) as u16,
+                submapper: header[8] >> 4,
+                prg_rom_size: RomHeader::rom_size(header[4], header[9] & 0x0F, PRG_ROM_UNIT),
+                chr_rom_size: RomHeader::rom_size(header[5], header[9] >> 4, CHR_ROM_UNIT),
+                prg_ram_size: RomHeader::ram_size(header[10] & 0x0F),
+                prg_nvram_size: RomHeader::ram_size(header[10] >> 4),
+                chr_ram_size: RomHeader::ram_size(header[11] & 0x0F),
+                chr_nvram_size: RomHeader::ram_size(header[11] >> 4),
+                vertical_mirroring,
+                four_screen,
+                battery,
+                trainer,
+                console_type,
+                timing: match header[12] & 0x03 {
+                    0 => Timing::Ntsc,
+                    1 => Timing::Pal,
+                    2 => Timing::MultiRegion,
+                    _ => Timing::Dendy,
+                },
+                misc_roms: header[14] & 0x03,
+                expansion_device: header[15] & 0x3F,
+            },
+            false => {
+                // old dumping tools wrote garbage ("DiskDude!") into bytes 7 - 15
+                let dirty = header[12..].iter().any(|byte| *byte != 0);
+                let (flags7, ram_amount, flags9) = match dirty {
+                    true  => (0, 0, 0),
+                    false => (header[7], header[8], header[9]),
+                };
+                let chr_rom_size = header[5] as usize * CHR_ROM_UNIT;
+                let ram_size = match ram_amount {
+                    0 => INES_RAM_SIZE,
+                    amount => amount as usize * INES_RAM_SIZE,
+                };
+                RomHeader {
+                    nes2,
+                    mapper: (flags7 & 0xF0 | header[6] >> 4) as u16,
+                    submapper: 0,
+                    prg_rom_size: header[4] as usize * PRG_ROM_UNIT,
+                    chr_rom_size,
+                    prg_ram_size: if battery { 0 } else { ram_size },
+                    prg_nvram_size: if battery { ram_size } else { 0 },
+                    chr_ram_size: if chr_rom_size == 0 { CHR_ROM_UNIT } else { 0 },
+                    chr_nvram_size: 0,
+                    vertical_mirroring,
+                    four_screen,
+                    battery,
+                    trainer,
+                    console_type: match console_type {
+                        _ if dirty => ConsoleType::Nes,
+                        ConsoleType::Extended(_) => ConsoleType::Nes,
+                        console_type => console_type,
+                    },
+                    timing: match flags9 & 0x01 != 0 {
+                        true  => Timing::Pal,
+                        false => Timing::Ntsc,
+                    },
+                    misc_roms: 0,
+                    expansion_device: 0,
+                }
+            },
+        };
+        Some(header)
+    }
+
+    // the msb nibble 0xF switches to the exponent-multiplier notation: 2^E * (MM * 2 + 1)
+    fn rom_size(lsb: u8, msb: u8, unit: usize) -> usize {
+        match msb {
+            0x0F => {
+                let exponent = (lsb >> 2) as u32;
+                let multiplier = (lsb & 0x03) as usize * 2 + 1;
+                2usize.saturating_pow(exponent).saturating_mul(multiplier)
+            },
+            _ => ((msb as usize) << 8 | lsb as usize) * unit,
+        }
+    }
+
+    // ram sizes are stored as a shift count: 64 << shift, 0 means no ram
+    fn ram_size(shift: u8) -> usize {
+        match shift {
+            0 => 0,
+            _ => 64 << shift,
+        }
+    }
+
+    pub fn prg_ram_total(&self) -> usize {
+        self.prg_ram_size + self.prg_nvram_size
+    }
+
+    pub fn chr_ram_total(&self) -> usize {
+        self.chr_ram_size + self.chr_nvram_size
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn header(bytes: &[u8]) -> Vec<u8> {
+        let mut data = b"NES\x1A".to_vec();
+        data.extend_from_slice(bytes);
+        data.resize(RomHeader::SIZE, 0);
+        data
+    }
+
+    #[test]
+    fn ines_header() {
+        // 32kb prg, 8kb chr, mapper 4, vertical mirroring, battery
+        let header = RomHeader::parse(&header(&[2, 1, 0x43, 0x00])).unwrap();
+        assert!(!header.nes2);
+        assert_eq!(header.mapper, 4);
+        assert_eq!(header.prg_rom_size, 32768);
+        assert_eq!(header.chr_rom_size, 8192);
+        assert!(header.vertical_mirroring);
+        assert!(header.battery);
+        assert_eq!(header.prg_ram_size, 0);
+        assert_eq!(header.prg_nvram_size, 8192);
+        assert_eq!(header.chr_ram_size, 0);
+        assert_eq!(header.timing, Timing::Ntsc);
+    }
+
+    #[test]
+    fn nes2_header_with_exponent_sizes() {
+        // mapper 261 submapper 2, prg 2^13 * 1, chr 2^10 * 3, 8kb prg ram, pal
+        let header = RomHeader::parse(&header(&[13 << 2, 10 << 2 | 1, 0x50, 0x08, 0x21, 0xFF, 0x07, 0x00, 0x01])).unwrap();
+        assert!(header.nes2);
+        assert_eq!(header.mapper, 261);
+        assert_eq!(header.submapper, 2);
+        assert_eq!(header.prg_rom_size, 8192);
+        assert_eq!(header.chr_rom_size, 3072);
+        assert_eq!(header.prg_ram_size, 8192);
+        assert_eq!(header.timing, Timing::Pal);
+    }
+
+    #[test]
+    fn nes2_header_with_huge_exponent() {
+        let header = RomHeader::parse(&header(&[63 << 2 | 3, 0, 0x00, 0x08, 0x00, 0x0F])).unwrap();
+        assert_eq!(header.prg_rom_size, usize::MAX);
+    }
+
+    #[test]
+    fn dirty_ines_header() {
+        // mapper 1 with "DiskDude!" in bytes 7 - 15
+        let mut bytes = vec![8, 0, 0x12];
+        bytes.extend_from_slice(b"DiskDude!");
+        let header = RomHeader::parse(&header(&bytes)).unwrap();
+        assert!(!header.nes2);
+        assert_eq!(header.mapper, 1);
+        assert_eq!(header.prg_rom_size, 131072);
+        assert_eq!(header.chr_ram_size, 8192);
+        assert_eq!(header.prg_nvram_size, 8192);
+        assert_eq!(header.console_type, ConsoleType::Nes);
+        assert_eq!(header.timing, Timing::Ntsc);
+    }
+
+    #[test]
+    fn wrong_signature() {
+        assert!(RomHeader::parse(b"NES\x1B\x02\x01\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00").is_none());
+        assert!(RomHeader::parse(b"NES\x1A").is_none());
+    }
+}
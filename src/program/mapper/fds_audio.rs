@@ -0,0 +1,267 @@
+// wavetable channel of the disk system: 64 steps of 6 bit samples, a volume envelope
+// and a frequency modulator with its own envelope
+
+// output at full volume is about 2.4 times louder than a pulse channel of the apu
+const MAX_OUTPUT: f32 = 0.36;
+// master volume 2/2, 2/3, 2/4, 2/5
+const MASTER_VOLUME: [u32; 4] = [36, 24, 17, 14];
+// modulation table steps, 4 resets the counter
+const MODULATION_STEPS: [i32; 8] = [0, 1, 2, 4, 0, -4, -2, -1];
+
+struct Envelope {
+    speed: u8,
+    gain: u8,
+    increase: bool,
+    disabled: bool,
+    timer: u32,
+}
+
+impl Envelope {
+    fn new() -> Envelope {
+        Envelope {
+            speed: 0,
+            gain: 0,
+            increase: false,
+            disabled: true,
+            timer: 0,
+        }
+    }
+
+    // DISS SSSS
+    fn write(&mut self, data: u8, master_speed: u8) {
+        self.speed = data & 0x3F;
+        self.increase = data & 0x40 != 0;
+        self.disabled = data & 0x80 != 0;
+        if self.disabled {
+            self.gain = self.speed;
+        }
+        self.reset_timer(master_speed);
+    }
+
+    fn reset_timer(&mut self, master_speed: u8) {
+        self.timer = 8 * (self.speed as u32 + 1) * master_speed as u32;
+    }
+
+    // returns true when the gain was changed
+    fn clock(&mut self, master_speed: u8) -> bool {
+        if self.disabled || master_speed == 0 {
+            return false;
+        }
+        if self.timer > 0 {
+            self.timer -= 1;
+        }
+        if self.timer > 0 {
+            return false;
+        }
+        self.reset_timer(master_speed);
+        if self.increase && self.gain < 32 {
+            self.gain += 1;
+        } else if !self.increase && self.gain > 0 {
+            self.gain -= 1;
+        }
+        true
+    }
+}
+
+pub struct FdsAudio {
+    wave_table: [u8; 64],
+    wave_write_enable: bool,
+    wave_halt: bool,
+    wave_frequency: u16,
+    wave_accumulator: u32,
+    wave_position: usize,
+    envelopes_halt: bool,
+    master_volume: usize,
+    master_envelope_speed: u8,
+    volume: Envelope,
+    // gain is latched when the wave starts a new period
+    output_gain: u8,
+
+    modulation: Envelope,
+    modulation_table: [u8; 64],
+    modulation_position: usize,
+    modulation_frequency: u16,
+    modulation_halt: bool,
+    modulation_accumulator: u32,
+    modulation_counter: i32, // 7 bit signed
+    pitch_offset: i32,
+
+    output: u8,
+}
+
+impl FdsAudio {
+    pub fn new() -> FdsAudio {
+        FdsAudio {
+            wave_table: [0; 64],
+            wave_write_enable: false,
+            wave_halt: true,
+            wave_frequency: 0,
+            wave_accumulator: 0,
+            wave_position: 0,
+            envelopes_halt: false,
+            master_volume: 0,
+            master_envelope_speed: 0xE8,
+            volume: Envelope::new(),
+            output_gain: 0,
+
+            modulation: Envelope::new(),
+            modulation_table: [0; 64],
+            modulation_position: 0,
+            modulation_frequency: 0,
+            modulation_halt: true,
+            modulation_accumulator: 0,
+            modulation_counter: 0,
+            pitch_offset: 0,
+
+            output: 0,
+        }
+    }
+
+    pub fn read_register(&self, address: u16) -> u8 {
+        // the high bits are open bus
+        match address {
+            0x4040..=0x407F => 0x40 | self.wave_table[(address & 0x3F) as usize],
+            0x4090 => 0x40 | self.volume.gain,
+            0x4092 => 0x40 | self.modulation.gain,
+            _ => 0x40,
+        }
+    }
+
+    pub fn write_register(&mut self, address: u16, data: u8) {
+        match address {
+            0x4040..=0x407F => {
+                if self.wave_write_enable {
+                    self.wave_table[(address & 0x3F) as usize] = data & 0x3F;
+                }
+            },
+            0x4080 => self.volume.write(data, self.master_envelope_speed),
+            0x4082 => self.wave_frequency = (self.wave_frequency & 0x0F00) | data as u16,
+            0x4083 => {
+                self.wave_frequency = (self.wave_frequency & 0x00FF) | ((data & 0x0F) as u16) << 8;
+                self.wave_halt = data & 0x80 != 0;
+                self.envelopes_halt = data & 0x40 != 0;
+                if self.wave_halt {
+                    self.wave_accumulator = 0;
+                    self.wave_position = 0;
+                }
+                if self.envelopes_halt {
+                    self.volume.reset_timer(self.master_envelope_speed);
+                    self.modulation.reset_timer(self.master_envelope_speed);
+                }
+            },
+            0x4084 => self.modulation.write(data, self.master_envelope_speed),
+            0x4085 => self.set_modulation_counter((data & 0x7F) as i32),
+            0x4086 => self.modulation_frequency = (self.modulation_frequency & 0x0F00) | data as u16,
+            0x4087 => {
+                self.modulation_frequency = (self.modulation_frequency & 0x00FF) | ((data & 0x0F) as u16) << 8;
+                self.modulation_halt = data & 0x80 != 0;
+                if self.modulation_halt {
+                    self.modulation_accumulator = 0;
+                }
+            },
+            0x4088 => {
+                // every write fills two entries of the table, only while the modulator is halted
+                if self.modulation_halt {
+                    self.modulation_table[self.modulation_position] = data & 0x07;
+                    self.modulation_table[(self.modulation_position + 1) & 0x3F] = data & 0x07;
+                    self.modulation_position = (self.modulation_position + 2) & 0x3F;
+                }
+            },
+            0x4089 => {
+                self.wave_write_enable = data & 0x80 != 0;
+                self.master_volume = (data & 0x03) as usize;
+            },
+            0x408A => self.master_envelope_speed = data,
+            _ => (),
+        }
+        if let 0x4082..=0x4085 = address {
+            self.update_pitch_offset();
+        }
+    }
+
+    fn set_modulation_counter(&mut self, value: i32) {
+        self.modulation_counter = match value {
+            v if v >= 64 => v - 128,
+            v if v < -64 => v + 128,
+            v => v,
+        };
+    }
+
+    // pitch offset of the wave, the rounding follows the hardware
+    fn update_pitch_offset(&mut self) {
+        let mut temp = self.modulation_counter * self.modulation.gain as i32;
+        let remainder = temp & 0x0F;
+        temp >>= 4;
+        if remainder > 0 && temp & 0x80 == 0 {
+            match self.modulation_counter < 0 {
+                true  => temp -= 1,
+                false => temp += 2,
+            }
+        }
+        if temp >= 192 {
+            temp -= 256;
+        } else if temp < -64 {
+            temp += 256;
+        }
+        temp *= self.wave_frequency as i32;
+        let remainder = temp & 0x3F;
+        temp >>= 6;
+        if remainder >= 32 {
+            temp += 1;
+        }
+        self.pitch_offset = temp;
+    }
+
+    fn clock_modulator(&mut self) -> bool {
+        if self.modulation_halt || self.modulation_frequency == 0 {
+            return false;
+        }
+        self.modulation_accumulator += self.modulation_frequency as u32;
+        if self.modulation_accumulator <= 0xFFFF {
+            return false;
+        }
+        self.modulation_accumulator -= 0x10000;
+        let step = self.modulation_table[self.modulation_position];
+        match step {
+            4 => self.set_modulation_counter(0),
+            _ => self.set_modulation_counter(self.modulation_counter + MODULATION_STEPS[step as usize]),
+        }
+        self.modulation_position = (self.modulation_position + 1) & 0x3F;
+        true
+    }
+
+    pub fn clock(&mut self) { // every cpu cycle
+        if !self.wave_halt && !self.envelopes_halt {
+            self.volume.clock(self.master_envelope_speed);
+            if self.modulation.clock(self.master_envelope_speed) {
+                self.update_pitch_offset();
+            }
+        }
+        if self.clock_modulator() {
+            self.update_pitch_offset();
+        }
+
+        if self.wave_halt {
+            self.wave_position = 0;
+        } else {
+            let pitch = self.wave_frequency as i32 + self.pitch_offset;
+            if pitch > 0 && !self.wave_write_enable {
+                self.wave_accumulator += pitch as u32;
+                if self.wave_accumulator > 0xFFFF {
+                    self.wave_accumulator -= 0x10000;
+                    self.wave_position = (self.wave_position + 1) & 0x3F;
+                    if self.wave_position == 0 {
+                        self.output_gain = self.volume.gain;
+                    }
+                }
+            }
+        }
+        let level = self.output_gain.min(32) as u32 * MASTER_VOLUME[self.master_volume];
+        self.output = (self.wave_table[self.wave_position] as u32 * level / 1152) as u8;
+    }
+
+    // in units of the apu mixer output
+    pub fn output(&self) -> f32 {
+        self.output as f32 / 63.0 * MAX_OUTPUT
+    }
+}
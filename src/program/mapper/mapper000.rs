@@ -2,6 +2,8 @@ use crate::program::Mirroring;
 
 use super::Mapper;
 
+const PRG_BLOCK_SIZE: usize = 16384;
+
 pub struct Mapper000 {
     prg_amount: usize,
     mirroring: Mirroring,
@@ -21,11 +23,8 @@ impl Mapper000 {
 impl Mapper for Mapper000 {
     fn prg_read_addr(&self, address: u16, cartridge_addr: &mut usize) -> bool {
         if address >= 0x8000 {
-            *cartridge_addr = match self.prg_amount {
-            1 => address & 0x3FFF,
-            2 => address & 0x7FFF,
-            _ => panic!("wrong size of size_prg: {}", self.prg_amount),
-            } as usize;
+            // 16kb roms are mirrored at 0xC000
+            *cartridge_addr = (address & 0x7FFF) as usize % (self.prg_amount * PRG_BLOCK_SIZE);
             return true;
         }
         false
@@ -43,10 +42,6 @@ impl Mapper for Mapper000 {
         false
     }
 
-    fn chr_write_addr(&mut self, _address: u16, _data: u8) {
-        
-    }
-
     fn mirroring(&self) -> Mirroring {
         self.mirroring
     }
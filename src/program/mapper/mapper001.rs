@@ -31,6 +31,7 @@ pub struct Mapper001 {
     prg_wrt_counter: u8,
 
     mirroring: Mirroring,
+    prg_ram_enable: bool,
     prg_bank_mode: PRG_MODE,
     chr_bank_mode: CHR_MODE,
 }
@@ -50,6 +51,7 @@ impl Mapper001 {
             prg_wrt_counter: 0,
 
             mirroring: Mirroring::UNDEFINED,
+            prg_ram_enable: true,
             prg_bank_mode: PRG_MODE::FIX_LAST_16,
             chr_bank_mode: CHR_MODE::K8,
         }
@@ -100,8 +102,8 @@ impl Mapper for Mapper001 {
             let reg_selector = address;
             if reg_selector >= 0x8000 && reg_selector < 0xA000 { // control reg
                 self.mirroring = match self.shift_reg & 0x3 {
-                    0 => Mirroring::UNDEFINED,
-                    1 => Mirroring::UNDEFINED,
+                    0 => Mirroring::SINGLE_SCREEN_LOW,
+                    1 => Mirroring::SINGLE_SCREEN_HIGH,
                     2 => Mirroring::VERTICAL,
                     3 => Mirroring::HORISONTAL,
                     _ => Mirroring::UNDEFINED,
@@ -137,15 +139,16 @@ impl Mapper for Mapper001 {
                 };
             } else if reg_selector >= 0xE000 { // prg bank
                 info!("{:04X}", self.shift_reg);
+                self.prg_ram_enable = self.shift_reg & 0x10 == 0;
                 match self.prg_bank_mode {
                     PRG_MODE::FIX_FIRST_16 => {
-                        self.high_bank_offset = PRG_BLOCK_SIZE * (self.shift_reg & 0xFF) as usize;
+                        self.high_bank_offset = PRG_BLOCK_SIZE * (self.shift_reg & 0x0F) as usize;
                     },
                     PRG_MODE::FIX_LAST_16 => {
-                        self.low_bank_offset = PRG_BLOCK_SIZE * (self.shift_reg & 0xFF) as usize;
+                        self.low_bank_offset = PRG_BLOCK_SIZE * (self.shift_reg & 0x0F) as usize;
                     },
                     PRG_MODE::SWITCH_32 => {
-                        let bank = ((self.shift_reg & 0xFF) >> 1) as usize;
+                        let bank = ((self.shift_reg & 0x0F) >> 1) as usize;
                         self.low_bank_offset = bank * PRG_BLOCK_SIZE;
                         self.high_bank_offset = (bank + 1) * PRG_BLOCK_SIZE;
                     },
@@ -166,11 +169,15 @@ impl Mapper for Mapper001 {
         false
     }
 
-    fn chr_write_addr(&mut self, address: u16, data: u8) {
-        
-    }
-
     fn mirroring(&self) -> Mirroring {
         self.mirroring
     }
+
+    fn prg_ram_addr(&self, address: u16, cartridge_addr: &mut usize) -> bool {
+        if address >= 0x6000 && address < 0x8000 && self.prg_ram_enable {
+            *cartridge_addr = (address & 0x1FFF) as usize;
+            return true;
+        }
+        false
+    }
 }
\ No newline at end of file
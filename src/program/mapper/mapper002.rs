@@ -0,0 +1,64 @@
+use crate::program::Mirroring;
+
+use super::Mapper;
+
+const PRG_BLOCK_SIZE: usize = 16384;
+
+// UxROM: switchable 16kb bank at 0x8000, last bank fixed at 0xC000, 8kb chr
+pub struct Mapper002 {
+    prg_amount: usize,
+    prg_bank: usize,
+    mirroring: Mirroring,
+    bus_conflicts: bool,
+}
+
+impl Mapper002 {
+    pub fn new(prg_amount: usize, mirroring_bit: u8, bus_conflicts: bool) -> Self {
+        let mirroring = match mirroring_bit {
+            0 => Mirroring::HORISONTAL,
+            _ => Mirroring::VERTICAL,
+        };
+        Mapper002 {
+            prg_amount,
+            prg_bank: 0,
+            mirroring,
+            bus_conflicts,
+        }
+    }
+}
+
+impl Mapper for Mapper002 {
+    fn prg_read_addr(&self, address: u16, cartridge_addr: &mut usize) -> bool {
+        if address >= 0x8000 && address < 0xC000 {
+            *cartridge_addr = self.prg_bank * PRG_BLOCK_SIZE + (address & 0x3FFF) as usize;
+            return true;
+        }
+        if address >= 0xC000 {
+            *cartridge_addr = (self.prg_amount - 1) * PRG_BLOCK_SIZE + (address & 0x3FFF) as usize;
+            return true;
+        }
+        false
+    }
+
+    fn prg_write_addr(&mut self, address: u16, data: u8) {
+        if address >= 0x8000 {
+            self.prg_bank = data as usize % self.prg_amount;
+        }
+    }
+
+    fn chr_read_addr(&self, address: u16, cartridge_addr: &mut usize) -> bool {
+        if address < 0x2000 {
+            *cartridge_addr = address as usize;
+            return true;
+        }
+        false
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+
+    fn bus_conflicts(&self) -> bool {
+        self.bus_conflicts
+    }
+}
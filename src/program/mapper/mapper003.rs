@@ -0,0 +1,65 @@
+use crate::program::Mirroring;
+
+use super::Mapper;
+
+const CHR_BLOCK_SIZE: usize = 8192;
+
+// CNROM: fixed 16kb or 32kb prg, switchable 8kb chr bank
+pub struct Mapper003 {
+    prg_amount: usize,
+    chr_amount: usize,
+    chr_bank: usize,
+    mirroring: Mirroring,
+    bus_conflicts: bool,
+}
+
+impl Mapper003 {
+    pub fn new(prg_amount: usize, chr_amount: usize, mirroring_bit: u8, bus_conflicts: bool) -> Self {
+        let mirroring = match mirroring_bit {
+            0 => Mirroring::HORISONTAL,
+            _ => Mirroring::VERTICAL,
+        };
+        Mapper003 {
+            prg_amount,
+            chr_amount: chr_amount.max(1),
+            chr_bank: 0,
+            mirroring,
+            bus_conflicts,
+        }
+    }
+}
+
+impl Mapper for Mapper003 {
+    fn prg_read_addr(&self, address: u16, cartridge_addr: &mut usize) -> bool {
+        if address >= 0x8000 {
+            *cartridge_addr = match self.prg_amount {
+                1 => address & 0x3FFF,
+                _ => address & 0x7FFF,
+            } as usize;
+            return true;
+        }
+        false
+    }
+
+    fn prg_write_addr(&mut self, address: u16, data: u8) {
+        if address >= 0x8000 {
+            self.chr_bank = data as usize % self.chr_amount;
+        }
+    }
+
+    fn chr_read_addr(&self, address: u16, cartridge_addr: &mut usize) -> bool {
+        if address < 0x2000 {
+            *cartridge_addr = self.chr_bank * CHR_BLOCK_SIZE + address as usize;
+            return true;
+        }
+        false
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+
+    fn bus_conflicts(&self) -> bool {
+        self.bus_conflicts
+    }
+}
@@ -0,0 +1,195 @@
+use super::Mapper;
+use crate::program::Mirroring;
+
+const PRG_BANK_SIZE: usize = 8192;
+const CHR_BANK_SIZE: usize = 1024;
+
+pub struct Mapper004 {
+    prg_banks: usize,
+    chr_banks: usize,
+
+    bank_select: usize,
+    bank_registers: [usize; 8], // R0 - R7
+    prg_inversion: bool,
+    chr_inversion: bool,
+
+    mirroring: Mirroring,
+    prg_ram_enable: bool,
+    prg_ram_write_protect: bool,
+
+    irq_latch: u8,
+    irq_counter: u8,
+    irq_reload: bool,
+    irq_enable: bool,
+    irq_pending: bool,
+    last_a12: bool,
+    a12_low_cycles: u8,
+}
+
+impl Mapper004 {
+    pub fn new(prg_amount: usize, chr_amount: usize, mirroring_bit: u8) -> Self {
+        let mirroring = match mirroring_bit {
+            0 => Mirroring::HORISONTAL,
+            _ => Mirroring::VERTICAL,
+        };
+        Mapper004 {
+            prg_banks: prg_amount * 2,
+            chr_banks: chr_amount.max(1) * 8,
+
+            bank_select: 0,
+            bank_registers: [0, 2, 4, 5, 6, 7, 0, 1],
+            prg_inversion: false,
+            chr_inversion: false,
+
+            mirroring,
+            prg_ram_enable: true,
+            prg_ram_write_protect: false,
+
+            irq_latch: 0,
+            irq_counter: 0,
+            irq_reload: false,
+            irq_enable: false,
+            irq_pending: false,
+            last_a12: false,
+            a12_low_cycles: 0,
+        }
+    }
+
+    // clocked by rising edge of PPU A12 (once per scanline while rendering),
+    // edges after A12 was low for less than 3 cpu cycles are filtered out
+    fn clock_irq_counter(&mut self) {
+        if self.irq_counter == 0 || self.irq_reload {
+            self.irq_counter = self.irq_latch;
+            self.irq_reload = false;
+        } else {
+            self.irq_counter -= 1;
+        }
+        if self.irq_counter == 0 && self.irq_enable {
+            self.irq_pending = true;
+        }
+    }
+}
+
+impl Mapper for Mapper004 {
+    fn prg_read_addr(&self, address: u16, cartridge_addr: &mut usize) -> bool {
+        let second_last = self.prg_banks.saturating_sub(2);
+        let bank = match address {
+            0x8000..=0x9FFF => match self.prg_inversion {
+                false => self.bank_registers[6],
+                true  => second_last,
+            },
+            0xA000..=0xBFFF => self.bank_registers[7],
+            0xC000..=0xDFFF => match self.prg_inversion {
+                false => second_last,
+                true  => self.bank_registers[6],
+            },
+            0xE000..=0xFFFF => self.prg_banks - 1,
+            _ => return false,
+        };
+        *cartridge_addr = (bank % self.prg_banks) * PRG_BANK_SIZE + (address & 0x1FFF) as usize;
+        true
+    }
+
+    fn prg_write_addr(&mut self, address: u16, data: u8) {
+        if address < 0x8000 {
+            return;
+        }
+        match address & 0xE001 {
+            0x8000 => { // bank select
+                self.bank_select = (data & 0x07) as usize;
+                self.prg_inversion = data & 0x40 != 0;
+                self.chr_inversion = data & 0x80 != 0;
+            },
+            0x8001 => { // bank data
+                self.bank_registers[self.bank_select] = match self.bank_select {
+                    6 | 7 => (data & 0x3F) as usize,
+                    _ => data as usize,
+                };
+            },
+            0xA000 => {
+                self.mirroring = match data & 0x01 {
+                    0 => Mirroring::VERTICAL,
+                    _ => Mirroring::HORISONTAL,
+                };
+            },
+            0xA001 => {
+                self.prg_ram_enable = data & 0x80 != 0;
+                self.prg_ram_write_protect = data & 0x40 != 0;
+            },
+            0xC000 => self.irq_latch = data,
+            0xC001 => {
+                self.irq_counter = 0;
+                self.irq_reload = true;
+            },
+            0xE000 => {
+                self.irq_enable = false;
+                self.irq_acknowledge();
+            },
+            0xE001 => self.irq_enable = true,
+            _ => (),
+        }
+    }
+
+    fn chr_read_addr(&self, address: u16, cartridge_addr: &mut usize) -> bool {
+        if address >= 0x2000 {
+            return false;
+        }
+        let address = match self.chr_inversion {
+            true  => address ^ 0x1000,
+            false => address,
+        };
+        let bank = match address & 0x1C00 {
+            0x0000 => self.bank_registers[0] & 0xFE,
+            0x0400 => self.bank_registers[0] | 0x01,
+            0x0800 => self.bank_registers[1] & 0xFE,
+            0x0C00 => self.bank_registers[1] | 0x01,
+            0x1000 => self.bank_registers[2],
+            0x1400 => self.bank_registers[3],
+            0x1800 => self.bank_registers[4],
+            _      => self.bank_registers[5],
+        };
+        *cartridge_addr = (bank % self.chr_banks) * CHR_BANK_SIZE + (address & 0x03FF) as usize;
+        true
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+
+    fn prg_ram_addr(&self, address: u16, cartridge_addr: &mut usize) -> bool {
+        if address >= 0x6000 && address < 0x8000 && self.prg_ram_enable {
+            *cartridge_addr = (address & 0x1FFF) as usize;
+            return true;
+        }
+        false
+    }
+
+    fn prg_ram_writable(&self) -> bool {
+        !self.prg_ram_write_protect
+    }
+
+    fn clock(&mut self) {
+        if !self.last_a12 {
+            self.a12_low_cycles = self.a12_low_cycles.saturating_add(1);
+        }
+    }
+
+    fn ppu_address(&mut self, address: u16) {
+        let a12 = address & 0x1000 != 0;
+        if a12 && !self.last_a12 && self.a12_low_cycles >= 3 {
+            self.clock_irq_counter();
+        }
+        if !a12 && self.last_a12 {
+            self.a12_low_cycles = 0;
+        }
+        self.last_a12 = a12;
+    }
+
+    fn irq_pending(&self) -> bool {
+        self.irq_pending
+    }
+
+    fn irq_acknowledge(&mut self) {
+        self.irq_pending = false;
+    }
+}
@@ -0,0 +1,437 @@
+use super::{Mapper, Nametable};
+use super::mmc5_audio::Mmc5Audio;
+use crate::program::Mirroring;
+
+const PRG_BANK_SIZE: usize = 8192;
+const EXRAM_SIZE: usize = 1024;
+// this ppu fetches the sprite patterns at once at dot 257, so there are no reads for about
+// 20 cpu cycles on every scanline, the MMC5 waits longer than that before it ends the frame
+const PPU_IDLE_CYCLES: u8 = 32;
+// tiles fetched on a scanline: 32 visible, 2 for the next line and 2 dummy nametable reads
+const NEXT_LINE_TILE: usize = 34;
+
+// MMC5 (ExROM): prg and chr banking in four modes, 1kb of ExRAM (extra nametable, extended attributes
+// or work ram), fill-mode nametable, vertical split, the 8x8 multiplier, a scanline irq and
+// two pulse channels with a pcm channel.
+// the ppu isn't connected to the mapper directly, scanlines are found by watching the ppu bus:
+// three reads of the same nametable address in a row happen only at the start of a scanline
+pub struct Mapper005 {
+    prg_banks: usize,
+
+    prg_mode: u8,
+    chr_mode: u8,
+    prg_ram_protect: [u8; 2],
+    exram_mode: u8,
+    nametable_mapping: u8,
+    fill_tile: u8,
+    fill_attribute: u8,
+    prg_registers: [u8; 5], // 0x5113 - 0x5117
+    chr_registers: [usize; 12], // 0x5120 - 0x512B
+    chr_upper: usize,
+    last_chr_set_b: bool,
+
+    split_control: u8,
+    split_scroll: u8,
+    split_page: usize,
+
+    irq_compare: u8,
+    irq_enable: bool,
+    irq_pending: bool,
+
+    multiplicand: u8,
+    multiplier: u8,
+
+    exram: [u8; EXRAM_SIZE],
+
+    // ppu state seen by the mapper
+    sprites_8x16: bool,
+    rendering: bool,
+    in_frame: bool,
+    scanline: i32,
+    last_ppu_address: Option<u16>,
+    address_matches: u8,
+    ppu_idle_cycles: u8,
+    tile: usize,
+    pattern_fetches: u8,
+    // the current background tile comes from the split region or has an extended attribute
+    split_fetch: bool,
+    split_y: usize,
+    split_column: usize,
+    extended_attribute: u8,
+
+    audio: Mmc5Audio,
+}
+
+impl Mapper005 {
+    pub fn new(prg_amount: usize) -> Self {
+        Mapper005 {
+            prg_banks: prg_amount * 2,
+
+            prg_mode: 3,
+            chr_mode: 0,
+            prg_ram_protect: [0, 0],
+            exram_mode: 0,
+            nametable_mapping: 0,
+            fill_tile: 0,
+            fill_attribute: 0,
+            prg_registers: [0, 0, 0, 0, 0xFF],
+            chr_registers: [0; 12],
+            chr_upper: 0,
+            last_chr_set_b: false,
+
+            split_control: 0,
+            split_scroll: 0,
+            split_page: 0,
+
+            irq_compare: 0,
+            irq_enable: false,
+            irq_pending: false,
+
+            multiplicand: 0xFF,
+            multiplier: 0xFF,
+
+            exram: [0; EXRAM_SIZE],
+
+            sprites_8x16: false,
+            rendering: false,
+            in_frame: false,
+            scanline: -1,
+            last_ppu_address: None,
+            address_matches: 0,
+            ppu_idle_cycles: 0,
+            tile: 0,
+            pattern_fetches: 0,
+            split_fetch: false,
+            split_y: 0,
+            split_column: 0,
+            extended_attribute: 0,
+
+            audio: Mmc5Audio::new(),
+        }
+    }
+
+    // rom flag and 8kb bank for 0x8000 - 0xFFFF
+    fn prg_bank(&self, address: u16) -> (bool, usize) {
+        let slot = ((address - 0x8000) >> 13) as usize;
+        let register = |idx: usize| {
+            let data = self.prg_registers[idx];
+            // 0x5117 always selects rom
+            (idx == 4 || data & 0x80 != 0, (data & 0x7F) as usize)
+        };
+        match (self.prg_mode, slot) {
+            (0, _) => (true, (self.prg_registers[4] as usize & 0x7C) + slot),
+            (1, 0..=1) | (2, 0..=1) => {
+                let (rom, bank) = register(2);
+                (rom, (bank & 0x7E) + slot)
+            },
+            (1, _) => (true, (self.prg_registers[4] as usize & 0x7E) + slot - 2),
+            (2, 2) => register(3),
+            (2, _) => register(4),
+            (_, slot) => register(slot + 1),
+        }
+    }
+
+    // 1, 2, 4 or 8kb banks, set A (0x5120 - 0x5127) is used for sprites and set B (0x5128 - 0x512B)
+    // for the background in the 8x16 sprite mode
+    fn chr_addr(&self, address: u16, set_b: bool) -> usize {
+        let address = address as usize;
+        let (size, register) = match (self.chr_mode, set_b) {
+            (0, false) => (0x2000, 7),
+            (0, true)  => (0x2000, 11),
+            (1, false) => (0x1000, if address < 0x1000 { 3 } else { 7 }),
+            (1, true)  => (0x1000, 11),
+            (2, false) => (0x0800, [1, 3, 5, 7][address >> 11]),
+            (2, true)  => (0x0800, [9, 11][(address >> 11) & 0x01]),
+            (_, false) => (0x0400, address >> 10),
+            (_, true)  => (0x0400, 8 + ((address >> 10) & 0x03)),
+        };
+        self.chr_registers[register] * size + (address % size)
+    }
+
+    fn background_chr_set_b(&self) -> bool {
+        match (self.sprites_8x16, self.in_frame) {
+            (false, _) => false,
+            (true, true) => !self.sprite_fetch(),
+            (true, false) => self.last_chr_set_b,
+        }
+    }
+
+    // after the two pattern reads of the last background tile the ppu fetches sprites
+    fn sprite_fetch(&self) -> bool {
+        self.pattern_fetches > 2
+    }
+
+    fn background_fetch(&self) -> bool {
+        self.in_frame && !self.sprite_fetch()
+    }
+
+    fn start_scanline(&mut self) {
+        self.tile = 2;
+        if self.in_frame {
+            self.scanline += 1;
+            if self.scanline == self.irq_compare as i32 {
+                self.irq_pending = true;
+            }
+        } else {
+            self.in_frame = true;
+            self.scanline = 0;
+        }
+    }
+
+    fn end_frame(&mut self) {
+        self.in_frame = false;
+        self.scanline = -1;
+        self.last_ppu_address = None;
+        self.tile = 1;
+    }
+
+    // split region and extended attribute of the background tile which fetch starts now
+    fn fetch_tile(&mut self, address: u16) {
+        let (column, line) = match self.tile >= NEXT_LINE_TILE {
+            true  => (self.tile - NEXT_LINE_TILE, self.scanline + 1),
+            false => (self.tile, self.scanline),
+        };
+        let split_tile = (self.split_control & 0x1F) as usize;
+        let in_split = match self.split_control & 0x40 != 0 {
+            true  => column >= split_tile,
+            false => column < split_tile,
+        };
+        self.split_fetch = self.split_control & 0x80 != 0 && self.exram_mode <= 1 && in_split && column < 32;
+        if self.split_fetch {
+            self.split_y = (line.max(0) as usize + self.split_scroll as usize) % 240;
+            self.split_column = column;
+        }
+        self.extended_attribute = self.exram[(address & 0x03FF) as usize];
+    }
+
+    // nametable slots from 0x5105: 0, 1 - ciram pages, 2 - ExRAM, 3 - fill mode
+    fn nametable_slot(&self, address: u16) -> u8 {
+        (self.nametable_mapping >> (((address >> 10) & 0x03) * 2)) & 0x03
+    }
+
+    fn extended_attributes(&self) -> bool {
+        self.exram_mode == 1 && self.background_fetch()
+    }
+}
+
+fn attribute_address(address: u16) -> bool {
+    address & 0x03FF >= 0x03C0
+}
+
+impl Mapper for Mapper005 {
+    fn prg_read_addr(&self, address: u16, cartridge_addr: &mut usize) -> bool {
+        if address >= 0x8000 {
+            let (rom, bank) = self.prg_bank(address);
+            if rom {
+                *cartridge_addr = (bank % self.prg_banks) * PRG_BANK_SIZE + (address & 0x1FFF) as usize;
+                return true;
+            }
+        }
+        false
+    }
+
+    fn prg_write_addr(&mut self, address: u16, data: u8) {
+        match address {
+            0x5000..=0x5015 => self.audio.write_register(address, data),
+            0x5100 => self.prg_mode = data & 0x03,
+            0x5101 => self.chr_mode = data & 0x03,
+            0x5102 => self.prg_ram_protect[0] = data & 0x03,
+            0x5103 => self.prg_ram_protect[1] = data & 0x03,
+            0x5104 => self.exram_mode = data & 0x03,
+            0x5105 => self.nametable_mapping = data,
+            0x5106 => self.fill_tile = data,
+            0x5107 => self.fill_attribute = data & 0x03,
+            0x5113..=0x5117 => self.prg_registers[(address - 0x5113) as usize] = data,
+            0x5120..=0x512B => {
+                let register = (address - 0x5120) as usize;
+                self.chr_registers[register] = data as usize | self.chr_upper << 8;
+                self.last_chr_set_b = register >= 8;
+            },
+            0x5130 => self.chr_upper = (data & 0x03) as usize,
+            0x5200 => self.split_control = data,
+            0x5201 => self.split_scroll = data,
+            0x5202 => self.split_page = data as usize,
+            0x5203 => self.irq_compare = data,
+            0x5204 => self.irq_enable = data & 0x80 != 0,
+            0x5205 => self.multiplicand = data,
+            0x5206 => self.multiplier = data,
+            0x5C00..=0x5FFF => {
+                let offset = (address - 0x5C00) as usize;
+                match self.exram_mode {
+                    // nametable modes can be written only while rendering
+                    0 | 1 => self.exram[offset] = if self.in_frame { data } else { 0 },
+                    2 => self.exram[offset] = data,
+                    _ => (),
+                }
+            },
+            _ => (),
+        }
+    }
+
+    fn prg_read_register(&mut self, address: u16, data: &mut u8) -> bool {
+        *data = match address {
+            0x5010 | 0x5015 => match self.audio.read_register(address) {
+                Some(value) => value,
+                None => return false,
+            },
+            0x5204 => {
+                let status = (self.irq_pending as u8) << 7 | (self.in_frame as u8) << 6;
+                self.irq_pending = false;
+                status
+            },
+            0x5205 => (self.multiplicand as u16 * self.multiplier as u16) as u8,
+            0x5206 => ((self.multiplicand as u16 * self.multiplier as u16) >> 8) as u8,
+            0x5C00..=0x5FFF if self.exram_mode >= 2 => self.exram[(address - 0x5C00) as usize],
+            // the cpu reads the nmi vector when the frame ends
+            0xFFFA | 0xFFFB => {
+                self.end_frame();
+                return false;
+            },
+            _ => return false,
+        };
+        true
+    }
+
+    // 0x6000 - 0x7FFF and rom slots switched to ram, 8kb banks
+    fn prg_ram_addr(&self, address: u16, cartridge_addr: &mut usize) -> bool {
+        let bank = match address {
+            0x6000..=0x7FFF => self.prg_registers[0] as usize,
+            0x8000..=0xFFFF => match self.prg_bank(address) {
+                (false, bank) => bank,
+                (true, _) => return false,
+            },
+            _ => return false,
+        };
+        *cartridge_addr = (bank & 0x07) * PRG_BANK_SIZE + (address & 0x1FFF) as usize;
+        true
+    }
+
+    fn prg_ram_writable(&self) -> bool {
+        self.prg_ram_protect == [0x02, 0x01]
+    }
+
+    fn chr_read_addr(&self, address: u16, cartridge_addr: &mut usize) -> bool {
+        if address >= 0x2000 {
+            return false;
+        }
+        *cartridge_addr = if self.split_fetch && self.background_fetch() {
+            self.split_page * 0x1000 + ((address & 0x0FF8) as usize | (self.split_y & 0x07))
+        } else if self.extended_attributes() {
+            let bank = (self.extended_attribute & 0x3F) as usize | self.chr_upper << 6;
+            bank * 0x1000 + (address & 0x0FFF) as usize
+        } else {
+            self.chr_addr(address, self.background_chr_set_b())
+        };
+        true
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        match self.nametable_mapping {
+            0x44 => Mirroring::VERTICAL,
+            0x00 => Mirroring::SINGLE_SCREEN_LOW,
+            0x55 => Mirroring::SINGLE_SCREEN_HIGH,
+            _ => Mirroring::HORISONTAL,
+        }
+    }
+
+    fn nametable_addr(&self, address: u16) -> Nametable {
+        if self.background_fetch() && (self.split_fetch || (self.extended_attributes() && attribute_address(address))) {
+            return Nametable::Mapper;
+        }
+        let offset = (address & 0x03FF) as usize;
+        match self.nametable_slot(address) {
+            0 => Nametable::Ciram(offset),
+            1 => Nametable::Ciram(0x0400 | offset),
+            _ => Nametable::Mapper,
+        }
+    }
+
+    fn read_nametable(&self, address: u16) -> u8 {
+        let attribute = attribute_address(address);
+        if self.split_fetch && self.background_fetch() {
+            let row = self.split_y / 8;
+            let column = self.split_column;
+            return match attribute {
+                false => self.exram[row * 32 + column],
+                true => {
+                    let data = self.exram[0x03C0 + (row / 4) * 8 + column / 4];
+                    let shift = ((row & 0x02) << 1) | (column & 0x02);
+                    ((data >> shift) & 0x03) * 0x55
+                },
+            };
+        }
+        if self.extended_attributes() && attribute {
+            return (self.extended_attribute >> 6) * 0x55;
+        }
+        match self.nametable_slot(address) {
+            2 if self.exram_mode <= 1 => self.exram[(address & 0x03FF) as usize],
+            3 if attribute => self.fill_attribute * 0x55,
+            3 => self.fill_tile,
+            _ => 0,
+        }
+    }
+
+    fn write_nametable(&mut self, address: u16, data: u8) {
+        if self.nametable_slot(address) == 2 && self.exram_mode <= 1 {
+            self.exram[(address & 0x03FF) as usize] = data;
+        }
+    }
+
+    fn clock(&mut self) {
+        if self.ppu_idle_cycles < PPU_IDLE_CYCLES {
+            self.ppu_idle_cycles += 1;
+            if self.ppu_idle_cycles == PPU_IDLE_CYCLES {
+                self.end_frame();
+            }
+        }
+        self.audio.clock();
+    }
+
+    fn ppu_address(&mut self, address: u16) {
+        self.ppu_idle_cycles = 0;
+        if !self.rendering {
+            return;
+        }
+        match self.last_ppu_address == Some(address) {
+            true  => self.address_matches += 1,
+            false => self.address_matches = 0,
+        }
+        self.last_ppu_address = Some(address);
+
+        if address >= 0x2000 && address < 0x3000 && !attribute_address(address) {
+            match self.address_matches == 2 {
+                true  => self.start_scanline(),
+                false => self.tile += 1,
+            }
+            self.pattern_fetches = 0;
+            self.fetch_tile(address);
+        } else if address < 0x2000 {
+            self.pattern_fetches = self.pattern_fetches.saturating_add(1);
+        }
+    }
+
+    fn ppu_register_write(&mut self, address: u16, data: u8) {
+        match address {
+            0x2000 => self.sprites_8x16 = data & 0x20 != 0,
+            0x2001 => {
+                self.rendering = data & 0x18 != 0;
+                if !self.rendering {
+                    self.end_frame();
+                }
+            },
+            _ => (),
+        }
+    }
+
+    fn irq_pending(&self) -> bool {
+        self.irq_pending && self.irq_enable
+    }
+
+    fn irq_acknowledge(&mut self) {
+        self.irq_pending = false;
+    }
+
+    fn audio_output(&self) -> f32 {
+        self.audio.output()
+    }
+}
@@ -0,0 +1,60 @@
+use crate::program::Mirroring;
+
+use super::Mapper;
+
+const PRG_BLOCK_SIZE: usize = 16384;
+
+// AxROM: switchable 32kb prg bank, single screen mirroring selected by bit 4
+pub struct Mapper007 {
+    prg_banks: usize,
+    prg_bank: usize,
+    mirroring: Mirroring,
+    bus_conflicts: bool,
+}
+
+impl Mapper007 {
+    pub fn new(prg_amount: usize, bus_conflicts: bool) -> Self {
+        Mapper007 {
+            prg_banks: (prg_amount / 2).max(1),
+            prg_bank: 0,
+            mirroring: Mirroring::SINGLE_SCREEN_LOW,
+            bus_conflicts,
+        }
+    }
+}
+
+impl Mapper for Mapper007 {
+    fn prg_read_addr(&self, address: u16, cartridge_addr: &mut usize) -> bool {
+        if address >= 0x8000 {
+            *cartridge_addr = self.prg_bank * PRG_BLOCK_SIZE * 2 + (address & 0x7FFF) as usize;
+            return true;
+        }
+        false
+    }
+
+    fn prg_write_addr(&mut self, address: u16, data: u8) {
+        if address >= 0x8000 {
+            self.prg_bank = (data & 0x07) as usize % self.prg_banks;
+            self.mirroring = match data & 0x10 {
+                0 => Mirroring::SINGLE_SCREEN_LOW,
+                _ => Mirroring::SINGLE_SCREEN_HIGH,
+            };
+        }
+    }
+
+    fn chr_read_addr(&self, address: u16, cartridge_addr: &mut usize) -> bool {
+        if address < 0x2000 {
+            *cartridge_addr = address as usize;
+            return true;
+        }
+        false
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+
+    fn bus_conflicts(&self) -> bool {
+        self.bus_conflicts
+    }
+}
@@ -0,0 +1,118 @@
+use super::Mapper;
+use crate::program::Mirroring;
+
+const PRG_BANK_SIZE: usize = 8192;
+const CHR_BANK_SIZE: usize = 4096;
+
+// chr banks of the MMC2 / MMC4: every 4kb pattern table has two banks (for the tiles $FD and $FE),
+// a latch selects one of them and flips when the ppu fetches the tile $FD or $FE from the table
+pub struct ChrLatch {
+    banks: [[usize; 2]; 2], // [table][FD, FE]
+    latches: [usize; 2],
+    // MMC2 watches only the first byte of the tile in the first table, MMC4 the whole tile
+    exact_first_table: bool,
+}
+
+impl ChrLatch {
+    pub fn new(exact_first_table: bool) -> ChrLatch {
+        ChrLatch {
+            banks: [[0; 2]; 2],
+            latches: [1, 1],
+            exact_first_table,
+        }
+    }
+
+    // 0xB000 - 0xEFFF
+    pub fn write_bank(&mut self, address: u16, data: u8) {
+        let register = ((address - 0xB000) >> 12) as usize;
+        self.banks[register >> 1][register & 0x01] = (data & 0x1F) as usize;
+    }
+
+    pub fn chr_addr(&self, address: u16, chr_banks: usize) -> usize {
+        let table = (address >> 12) as usize & 0x01;
+        let bank = self.banks[table][self.latches[table]] % chr_banks;
+        bank * CHR_BANK_SIZE + (address & 0x0FFF) as usize
+    }
+
+    // the fetch which triggers the latch still reads the old bank
+    pub fn ppu_read(&mut self, address: u16) {
+        let table = (address >> 12) as usize & 0x01;
+        let tile_address = address & 0x0FF8;
+        let first_byte = address & 0x0FFF == tile_address;
+        if table == 0 && self.exact_first_table && !first_byte {
+            return;
+        }
+        match tile_address {
+            0x0FD8 => self.latches[table] = 0,
+            0x0FE8 => self.latches[table] = 1,
+            _ => (),
+        }
+    }
+}
+
+pub fn mirroring(data: u8) -> Mirroring {
+    match data & 0x01 {
+        0 => Mirroring::VERTICAL,
+        _ => Mirroring::HORISONTAL,
+    }
+}
+
+// MMC2 (PxROM): switchable 8kb bank at 0x8000, the last three 8kb banks are fixed, latched 4kb chr banks
+pub struct Mapper009 {
+    prg_banks: usize,
+    chr_banks: usize,
+    prg_bank: usize,
+    chr_latch: ChrLatch,
+    mirroring: Mirroring,
+}
+
+impl Mapper009 {
+    pub fn new(prg_amount: usize, chr_amount: usize) -> Self {
+        Mapper009 {
+            prg_banks: prg_amount * 2,
+            chr_banks: chr_amount.max(1) * 2,
+            prg_bank: 0,
+            chr_latch: ChrLatch::new(true),
+            mirroring: Mirroring::VERTICAL,
+        }
+    }
+}
+
+impl Mapper for Mapper009 {
+    fn prg_read_addr(&self, address: u16, cartridge_addr: &mut usize) -> bool {
+        if address >= 0x8000 {
+            let bank = match address {
+                0x8000..=0x9FFF => self.prg_bank,
+                _ => self.prg_banks.saturating_sub(4) + ((address - 0x8000) as usize >> 13),
+            };
+            *cartridge_addr = (bank % self.prg_banks) * PRG_BANK_SIZE + (address & 0x1FFF) as usize;
+            return true;
+        }
+        false
+    }
+
+    fn prg_write_addr(&mut self, address: u16, data: u8) {
+        match address {
+            0xA000..=0xAFFF => self.prg_bank = (data & 0x0F) as usize,
+            0xB000..=0xEFFF => self.chr_latch.write_bank(address, data),
+            0xF000..=0xFFFF => self.mirroring = mirroring(data),
+            _ => (),
+        }
+    }
+
+    fn chr_read_addr(&self, address: u16, cartridge_addr: &mut usize) -> bool {
+        if address < 0x2000 {
+            *cartridge_addr = self.chr_latch.chr_addr(address, self.chr_banks);
+            return true;
+        }
+        false
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+
+    fn ppu_read(&mut self, address: u16) {
+        self.chr_latch.ppu_read(address);
+    }
+}
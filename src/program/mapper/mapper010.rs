@@ -0,0 +1,66 @@
+use super::Mapper;
+use super::mapper009::{self, ChrLatch};
+use crate::program::Mirroring;
+
+const PRG_BANK_SIZE: usize = 16384;
+
+// MMC4 (FxROM): switchable 16kb bank at 0x8000, the last bank is fixed at 0xC000,
+// latched 4kb chr banks like the MMC2 and 8kb of prg ram
+pub struct Mapper010 {
+    prg_banks: usize,
+    chr_banks: usize,
+    prg_bank: usize,
+    chr_latch: ChrLatch,
+    mirroring: Mirroring,
+}
+
+impl Mapper010 {
+    pub fn new(prg_amount: usize, chr_amount: usize) -> Self {
+        Mapper010 {
+            prg_banks: prg_amount,
+            chr_banks: chr_amount.max(1) * 2,
+            prg_bank: 0,
+            chr_latch: ChrLatch::new(false),
+            mirroring: Mirroring::VERTICAL,
+        }
+    }
+}
+
+impl Mapper for Mapper010 {
+    fn prg_read_addr(&self, address: u16, cartridge_addr: &mut usize) -> bool {
+        if address >= 0x8000 {
+            let bank = match address {
+                0x8000..=0xBFFF => self.prg_bank % self.prg_banks,
+                _ => self.prg_banks - 1,
+            };
+            *cartridge_addr = bank * PRG_BANK_SIZE + (address & 0x3FFF) as usize;
+            return true;
+        }
+        false
+    }
+
+    fn prg_write_addr(&mut self, address: u16, data: u8) {
+        match address {
+            0xA000..=0xAFFF => self.prg_bank = (data & 0x0F) as usize,
+            0xB000..=0xEFFF => self.chr_latch.write_bank(address, data),
+            0xF000..=0xFFFF => self.mirroring = mapper009::mirroring(data),
+            _ => (),
+        }
+    }
+
+    fn chr_read_addr(&self, address: u16, cartridge_addr: &mut usize) -> bool {
+        if address < 0x2000 {
+            *cartridge_addr = self.chr_latch.chr_addr(address, self.chr_banks);
+            return true;
+        }
+        false
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+
+    fn ppu_read(&mut self, address: u16) {
+        self.chr_latch.ppu_read(address);
+    }
+}
@@ -0,0 +1,150 @@
+use super::{Mapper, Nametable};
+use super::n163_audio::N163Audio;
+use crate::program::Mirroring;
+
+const PRG_BANK_SIZE: usize = 8192;
+const CHR_BANK_SIZE: usize = 1024;
+
+// Namco 163: three switchable 8kb prg banks, eight 1kb chr banks, four nametable registers selecting
+// either the internal vram of the console or 1kb chr rom banks, a 15 bit irq counter counting up every
+// cpu cycle and the wavetable sound
+pub struct Mapper019 {
+    prg_banks: usize,
+
+    prg_registers: [usize; 3],
+    chr_registers: [usize; 8],
+    nametable_registers: [usize; 4],
+    sound_disabled: bool,
+    // 0x4- of 0xF800 allows prg ram writes
+    write_protect: u8,
+
+    irq_counter: u16,
+    irq_enable: bool,
+    irq_pending: bool,
+    audio: N163Audio,
+}
+
+impl Mapper019 {
+    pub fn new(prg_amount: usize) -> Self {
+        Mapper019 {
+            prg_banks: prg_amount * 2,
+
+            prg_registers: [0, 1, 2],
+            chr_registers: [0; 8],
+            nametable_registers: [0xE0, 0xE1, 0xE0, 0xE1],
+            sound_disabled: false,
+            write_protect: 0,
+
+            irq_counter: 0,
+            irq_enable: false,
+            irq_pending: false,
+            audio: N163Audio::new(),
+        }
+    }
+}
+
+impl Mapper for Mapper019 {
+    fn prg_read_addr(&self, address: u16, cartridge_addr: &mut usize) -> bool {
+        let bank = match address {
+            0x8000..=0xDFFF => self.prg_registers[((address - 0x8000) >> 13) as usize],
+            0xE000..=0xFFFF => self.prg_banks - 1,
+            _ => return false,
+        };
+        *cartridge_addr = (bank % self.prg_banks) * PRG_BANK_SIZE + (address & 0x1FFF) as usize;
+        true
+    }
+
+    fn prg_read_register(&mut self, address: u16, data: &mut u8) -> bool {
+        *data = match address {
+            0x4800..=0x4FFF => self.audio.read_data(),
+            0x5000..=0x57FF => self.irq_counter as u8,
+            0x5800..=0x5FFF => (self.irq_counter >> 8) as u8 | (self.irq_enable as u8) << 7,
+            _ => return false,
+        };
+        true
+    }
+
+    fn prg_write_addr(&mut self, address: u16, data: u8) {
+        match address {
+            0x4800..=0x4FFF => self.audio.write_data(data),
+            0x5000..=0x57FF => {
+                self.irq_counter = (self.irq_counter & 0x7F00) | data as u16;
+                self.irq_pending = false;
+            },
+            0x5800..=0x5FFF => {
+                self.irq_counter = (self.irq_counter & 0x00FF) | ((data & 0x7F) as u16) << 8;
+                self.irq_enable = data & 0x80 != 0;
+                self.irq_pending = false;
+            },
+            0x8000..=0xBFFF => self.chr_registers[((address - 0x8000) >> 11) as usize] = data as usize,
+            0xC000..=0xDFFF => self.nametable_registers[((address - 0xC000) >> 11) as usize] = data as usize,
+            0xE000..=0xE7FF => {
+                self.prg_registers[0] = (data & 0x3F) as usize;
+                self.sound_disabled = data & 0x40 != 0;
+            },
+            0xE800..=0xEFFF => self.prg_registers[1] = (data & 0x3F) as usize,
+            0xF000..=0xF7FF => self.prg_registers[2] = (data & 0x3F) as usize,
+            0xF800..=0xFFFF => {
+                self.audio.write_address(data);
+                self.write_protect = data;
+            },
+            _ => (),
+        }
+    }
+
+    // chr banks 0xE0 - 0xFF can also select the console vram as pattern table (bits 6 and 7 of 0xE800),
+    // this is not supported and they read chr rom
+    fn chr_read_addr(&self, address: u16, cartridge_addr: &mut usize) -> bool {
+        if address < 0x2000 {
+            let bank = self.chr_registers[(address >> 10) as usize];
+            *cartridge_addr = bank * CHR_BANK_SIZE + (address & 0x03FF) as usize;
+            return true;
+        }
+        false
+    }
+
+    // the per 2kb write protection of the lower bits is not supported
+    fn prg_ram_writable(&self) -> bool {
+        self.write_protect & 0xF0 == 0x40
+    }
+
+    // not used, the nametable registers take over the nametables
+    fn mirroring(&self) -> Mirroring {
+        Mirroring::VERTICAL
+    }
+
+    fn nametable_addr(&self, address: u16) -> Nametable {
+        let offset = (address & 0x03FF) as usize;
+        match self.nametable_registers[((address >> 10) & 0x03) as usize] {
+            bank @ 0xE0..=0xFF => Nametable::Ciram((bank & 0x01) << 10 | offset),
+            bank => Nametable::Chr(bank * CHR_BANK_SIZE + offset),
+        }
+    }
+
+    fn clock(&mut self) {
+        if self.irq_enable && self.irq_counter < 0x7FFF {
+            self.irq_counter += 1;
+            if self.irq_counter == 0x7FFF {
+                self.irq_pending = true;
+            }
+        }
+        if !self.sound_disabled {
+            self.audio.clock();
+        }
+    }
+
+    fn irq_pending(&self) -> bool {
+        self.irq_pending
+    }
+
+    fn irq_acknowledge(&mut self) {
+        self.irq_pending = false;
+    }
+
+    fn audio_output(&self) -> f32 {
+        match self.sound_disabled {
+            true  => 0.0,
+            false => self.audio.output(),
+        }
+    }
+}
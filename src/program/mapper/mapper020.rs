@@ -0,0 +1,395 @@
+use super::Mapper;
+use super::fds_audio::FdsAudio;
+use crate::program::Mirroring;
+
+// Famicom Disk System: the RAM adapter has 32kb of prg ram (0x6000 - 0xDFFF), 8kb of chr ram,
+// the bios at 0xE000, a timer irq, the disk drive interface and a wavetable sound channel
+
+// cpu cycles between two bytes passing the disk head
+const BYTE_DELAY: u32 = 149;
+// cpu cycles for the head to get from the end of the disk back to its start
+const REWIND_DELAY: u32 = 50000;
+
+pub struct Mapper020 {
+    sides: Vec<Vec<u8>>,
+    // disk data as it was loaded, changes are saved as a diff to it
+    original_sides: Vec<Vec<u8>>,
+    sides_changed: bool,
+    side: Option<usize>,
+
+    mirroring: Mirroring,
+    disk_io_enable: bool,
+    sound_io_enable: bool,
+
+    timer_reload: u16,
+    timer_counter: u16,
+    timer_repeat: bool,
+    timer_enable: bool,
+    timer_irq: bool,
+
+    motor_on: bool,
+    reset_transfer: bool,
+    read_mode: bool,
+    crc_control: bool,
+    previous_crc_control: bool,
+    transfer_enable: bool,
+    disk_irq_enable: bool,
+    disk_irq: bool,
+
+    read_data: u8,
+    write_data: u8,
+    transfer_complete: bool,
+    end_of_head: bool,
+    scanning: bool,
+    gap_ended: bool,
+    position: usize,
+    delay: u32,
+    crc: u16,
+
+    audio: FdsAudio,
+}
+
+impl Mapper020 {
+    pub fn new(sides: Vec<Vec<u8>>) -> Self {
+        Mapper020 {
+            original_sides: sides.clone(),
+            side: if sides.is_empty() { None } else { Some(0) },
+            sides,
+            sides_changed: false,
+
+            mirroring: Mirroring::HORISONTAL,
+            disk_io_enable: true,
+            sound_io_enable: true,
+
+            timer_reload: 0,
+            timer_counter: 0,
+            timer_repeat: false,
+            timer_enable: false,
+            timer_irq: false,
+
+            motor_on: false,
+            reset_transfer: false,
+            read_mode: true,
+            crc_control: false,
+            previous_crc_control: false,
+            transfer_enable: false,
+            disk_irq_enable: false,
+            disk_irq: false,
+
+            read_data: 0,
+            write_data: 0,
+            transfer_complete: false,
+            end_of_head: true,
+            scanning: false,
+            gap_ended: false,
+            position: 0,
+            delay: 0,
+            crc: 0,
+
+            audio: FdsAudio::new(),
+        }
+    }
+
+    fn update_crc(&mut self, data: u8) {
+        for bit in 0..8 {
+            let carry = self.crc & 0x01 != 0;
+            self.crc >>= 1;
+            if carry {
+                self.crc ^= 0x8408;
+            }
+            if data & (1 << bit) != 0 {
+                self.crc ^= 0x8000;
+            }
+        }
+    }
+
+    fn clock_timer(&mut self) {
+        if !self.timer_enable {
+            return;
+        }
+        if self.timer_counter == 0 {
+            self.timer_irq = true;
+            self.timer_counter = self.timer_reload;
+            if !self.timer_repeat {
+                self.timer_enable = false;
+            }
+        } else {
+            self.timer_counter -= 1;
+        }
+    }
+
+    fn clock_disk(&mut self) {
+        let side = match self.side {
+            Some(side) if self.motor_on => side,
+            _ => {
+                self.end_of_head = true;
+                self.scanning = false;
+                return;
+            },
+        };
+        if self.reset_transfer && !self.scanning {
+            return;
+        }
+        if self.end_of_head {
+            self.delay = REWIND_DELAY;
+            self.end_of_head = false;
+            self.position = 0;
+            self.gap_ended = false;
+            return;
+        }
+        if self.delay > 0 {
+            self.delay -= 1;
+            return;
+        }
+        // a shorter side may have been inserted since the head was rewound
+        if self.position >= self.sides[side].len() {
+            self.end_of_head = true;
+            return;
+        }
+
+        self.scanning = true;
+        let mut irq = self.disk_irq_enable;
+        if self.read_mode {
+            let data = self.sides[side][self.position];
+            if !self.previous_crc_control {
+                self.update_crc(data);
+            }
+            if !self.transfer_enable {
+                self.gap_ended = false;
+                self.crc = 0;
+            } else if data != 0 && !self.gap_ended {
+                // the start mark of a block ends the gap, it isn't passed to the cpu
+                self.gap_ended = true;
+                irq = false;
+            }
+            if self.gap_ended {
+                self.transfer_complete = true;
+                self.read_data = data;
+                if irq {
+                    self.disk_irq = true;
+                }
+            }
+        } else {
+            let mut data = 0;
+            if !self.crc_control {
+                self.transfer_complete = true;
+                data = self.write_data;
+                if irq {
+                    self.disk_irq = true;
+                }
+            }
+            if !self.transfer_enable {
+                data = 0;
+            }
+            if !self.crc_control {
+                self.update_crc(data);
+            } else {
+                if !self.previous_crc_control {
+                    self.update_crc(0);
+                    self.update_crc(0);
+                }
+                data = self.crc as u8;
+                self.crc >>= 8;
+            }
+            if self.sides[side][self.position] != data {
+                self.sides[side][self.position] = data;
+                self.sides_changed = true;
+            }
+            self.gap_ended = false;
+        }
+        self.previous_crc_control = self.crc_control;
+
+        self.position += 1;
+        if self.position >= self.sides[side].len() {
+            self.motor_on = false;
+            self.end_of_head = true;
+            if irq {
+                self.disk_irq = true;
+            }
+        } else {
+            self.delay = BYTE_DELAY;
+        }
+    }
+
+    fn concat_sides(sides: &[Vec<u8>]) -> Vec<u8> {
+        sides.iter().flatten().copied().collect()
+    }
+
+    pub fn disk_sides(&self) -> usize {
+        self.sides.len()
+    }
+
+    pub fn disk_side(&self) -> Option<usize> {
+        self.side
+    }
+
+    pub fn insert_disk(&mut self, side: Option<usize>) {
+        self.side = side.filter(|side| *side < self.sides.len());
+        self.end_of_head = true;
+        self.scanning = false;
+    }
+
+    // changes of the disks are saved as an ips diff of all sides, Some when there are unsaved changes
+    pub fn save_data(&self) -> Option<Vec<u8>> {
+        if !self.sides_changed {
+            return None;
+        }
+        let original = Mapper020::concat_sides(&self.original_sides);
+        Some(crate::program::create_ips(&original, &Mapper020::concat_sides(&self.sides)))
+    }
+
+    pub fn save_data_written(&mut self) {
+        self.sides_changed = false;
+    }
+
+    pub fn load_save_data(&mut self, data: &[u8]) {
+        let original = Mapper020::concat_sides(&self.original_sides);
+        match crate::program::apply_patch(&original, data) {
+            Ok(disk) => {
+                let mut offset = 0;
+                for side in self.sides.iter_mut() {
+                    let size = side.len();
+                    if let Some(data) = disk.get(offset..offset + size) {
+                        side.copy_from_slice(data);
+                    }
+                    offset += size;
+                }
+                info!("fds: disk changes loaded");
+            },
+            Err(e) => error!("fds: can't load disk changes: {}", e),
+        }
+    }
+}
+
+impl Mapper for Mapper020 {
+    fn prg_read_addr(&self, address: u16, cartridge_addr: &mut usize) -> bool {
+        if address >= 0xE000 {
+            *cartridge_addr = (address & 0x1FFF) as usize;
+            return true;
+        }
+        false
+    }
+
+    fn prg_write_addr(&mut self, address: u16, data: u8) {
+        match address {
+            0x4023 => {
+                self.disk_io_enable = data & 0x01 != 0;
+                self.sound_io_enable = data & 0x02 != 0;
+                if !self.disk_io_enable {
+                    self.timer_enable = false;
+                    self.timer_irq = false;
+                    self.disk_irq = false;
+                }
+            },
+            0x4020..=0x4026 if self.disk_io_enable => match address {
+                0x4020 => self.timer_reload = (self.timer_reload & 0xFF00) | data as u16,
+                0x4021 => self.timer_reload = (self.timer_reload & 0x00FF) | (data as u16) << 8,
+                0x4022 => {
+                    self.timer_repeat = data & 0x01 != 0;
+                    self.timer_enable = data & 0x02 != 0;
+                    match self.timer_enable {
+                        true  => self.timer_counter = self.timer_reload,
+                        false => self.timer_irq = false,
+                    }
+                },
+                0x4024 => {
+                    self.write_data = data;
+                    self.transfer_complete = false;
+                    self.disk_irq = false;
+                },
+                0x4025 => {
+                    self.motor_on = data & 0x01 != 0;
+                    self.reset_transfer = data & 0x02 != 0;
+                    self.read_mode = data & 0x04 != 0;
+                    self.mirroring = match data & 0x08 {
+                        0 => Mirroring::VERTICAL,
+                        _ => Mirroring::HORISONTAL,
+                    };
+                    self.crc_control = data & 0x10 != 0;
+                    self.transfer_enable = data & 0x40 != 0;
+                    self.disk_irq_enable = data & 0x80 != 0;
+                    self.disk_irq = false;
+                },
+                _ => (),
+            },
+            0x4040..=0x408A if self.sound_io_enable => self.audio.write_register(address, data),
+            _ => (),
+        }
+    }
+
+    fn prg_read_register(&mut self, address: u16, data: &mut u8) -> bool {
+        match address {
+            0x4030..=0x4033 if self.disk_io_enable => {
+                *data = match address {
+                    0x4030 => {
+                        let mut status = 0;
+                        if self.timer_irq { status |= 0x01 }
+                        if self.transfer_complete { status |= 0x02 }
+                        if self.end_of_head { status |= 0x40 }
+                        self.transfer_complete = false;
+                        self.timer_irq = false;
+                        self.disk_irq = false;
+                        status
+                    },
+                    0x4031 => {
+                        self.transfer_complete = false;
+                        self.disk_irq = false;
+                        self.read_data
+                    },
+                    0x4032 => {
+                        let mut status = 0x40;
+                        if self.side.is_none() { status |= 0x05 } // no disk, write protected
+                        if self.side.is_none() || !self.scanning { status |= 0x02 }
+                        status
+                    },
+                    _ => 0x80, // battery is good
+                };
+                true
+            },
+            0x4040..=0x4097 if self.sound_io_enable => {
+                *data = self.audio.read_register(address);
+                true
+            },
+            _ => false,
+        }
+    }
+
+    fn prg_ram_addr(&self, address: u16, cartridge_addr: &mut usize) -> bool {
+        if address >= 0x6000 && address < 0xE000 {
+            *cartridge_addr = (address - 0x6000) as usize;
+            return true;
+        }
+        false
+    }
+
+    fn chr_read_addr(&self, address: u16, cartridge_addr: &mut usize) -> bool {
+        if address < 0x2000 {
+            *cartridge_addr = address as usize;
+            return true;
+        }
+        false
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+
+    fn clock(&mut self) {
+        self.clock_timer();
+        self.clock_disk();
+        self.audio.clock();
+    }
+
+    fn irq_pending(&self) -> bool {
+        self.timer_irq || self.disk_irq
+    }
+
+    fn irq_acknowledge(&mut self) {
+        self.timer_irq = false;
+        self.disk_irq = false;
+    }
+
+    fn audio_output(&self) -> f32 {
+        self.audio.output()
+    }
+}
@@ -0,0 +1,146 @@
+use super::Mapper;
+use super::vrc_irq::VrcIrq;
+use crate::program::Mirroring;
+
+const PRG_BANK_SIZE: usize = 8192;
+const CHR_BANK_SIZE: usize = 1024;
+
+// Konami VRC2 / VRC4 (mappers 21, 22, 23 and 25): two switchable 8kb prg banks, eight 1kb chr banks and
+// (VRC4 only) the irq counter. the boards connect different cpu address lines to the two register
+// select pins of the chip, the submapper tells which ones; without it both possible lines are used
+pub struct Mapper021 {
+    prg_banks: usize,
+    // cpu address lines of the register select pins 0 and 1
+    select_lines: (u16, u16),
+    vrc2: bool,
+    // VRC2a ignores the lowest bit of the chr banks
+    chr_shift: usize,
+
+    prg_registers: [usize; 2],
+    prg_swap: bool,
+    chr_registers: [usize; 8],
+    mirroring: Mirroring,
+    irq: VrcIrq,
+}
+
+impl Mapper021 {
+    pub fn new(prg_amount: usize, mapper: u16, submapper: u8) -> Self {
+        let (select_lines, vrc2) = match (mapper, submapper) {
+            (21, 1) => ((0x02, 0x04), false), // VRC4a
+            (21, 2) => ((0x40, 0x80), false), // VRC4c
+            (21, _) => ((0x42, 0x84), false),
+            (22, _) => ((0x02, 0x01), true),  // VRC2a
+            (23, 1) => ((0x01, 0x02), false), // VRC4f
+            (23, 2) => ((0x04, 0x08), false), // VRC4e
+            (23, 3) => ((0x01, 0x02), true),  // VRC2b
+            (23, _) => ((0x05, 0x0A), false),
+            (25, 1) => ((0x02, 0x01), false), // VRC4b
+            (25, 2) => ((0x08, 0x04), false), // VRC4d
+            (25, 3) => ((0x02, 0x01), true),  // VRC2c
+            (_, _)  => ((0x0A, 0x05), false),
+        };
+        Mapper021 {
+            prg_banks: prg_amount * 2,
+            select_lines,
+            vrc2,
+            chr_shift: if mapper == 22 { 1 } else { 0 },
+
+            prg_registers: [0, 1],
+            prg_swap: false,
+            chr_registers: [0; 8],
+            mirroring: Mirroring::VERTICAL,
+            irq: VrcIrq::new(),
+        }
+    }
+
+    fn register_select(&self, address: u16) -> u16 {
+        let (line_0, line_1) = self.select_lines;
+        (address & line_0 != 0) as u16 | ((address & line_1 != 0) as u16) << 1
+    }
+}
+
+impl Mapper for Mapper021 {
+    fn prg_read_addr(&self, address: u16, cartridge_addr: &mut usize) -> bool {
+        if address >= 0x8000 {
+            let second_last = self.prg_banks - 2;
+            let bank = match (address >> 13) & 0x03 {
+                0 if self.prg_swap => second_last,
+                0 => self.prg_registers[0],
+                1 => self.prg_registers[1],
+                2 if self.prg_swap => self.prg_registers[0],
+                2 => second_last,
+                _ => self.prg_banks - 1,
+            };
+            *cartridge_addr = (bank % self.prg_banks) * PRG_BANK_SIZE + (address & 0x1FFF) as usize;
+            return true;
+        }
+        false
+    }
+
+    fn prg_write_addr(&mut self, address: u16, data: u8) {
+        if address < 0x8000 {
+            return;
+        }
+        let select = self.register_select(address);
+        match (address & 0xF000, select) {
+            (0x8000, _) => self.prg_registers[0] = (data & 0x1F) as usize,
+            (0x9000, _) if self.vrc2 => {
+                self.mirroring = match data & 0x01 {
+                    0 => Mirroring::VERTICAL,
+                    _ => Mirroring::HORISONTAL,
+                };
+            },
+            (0x9000, 0..=1) => {
+                self.mirroring = match data & 0x03 {
+                    0 => Mirroring::VERTICAL,
+                    1 => Mirroring::HORISONTAL,
+                    2 => Mirroring::SINGLE_SCREEN_LOW,
+                    _ => Mirroring::SINGLE_SCREEN_HIGH,
+                };
+            },
+            (0x9000, _) => self.prg_swap = data & 0x02 != 0,
+            (0xA000, _) => self.prg_registers[1] = (data & 0x1F) as usize,
+            (0xB000..=0xE000, select) => {
+                // every bank has a register for its low and high nibble
+                let bank = ((address >> 12) as usize - 0x0B) * 2 + (select >> 1) as usize;
+                let value = self.chr_registers[bank];
+                self.chr_registers[bank] = match select & 0x01 {
+                    0 => (value & 0x1F0) | (data & 0x0F) as usize,
+                    _ if self.vrc2 => (value & 0x0F) | ((data & 0x0F) as usize) << 4,
+                    _ => (value & 0x0F) | ((data & 0x1F) as usize) << 4,
+                };
+            },
+            (0xF000, _) if self.vrc2 => (),
+            (0xF000, 0) => self.irq.set_latch_low(data),
+            (0xF000, 1) => self.irq.set_latch_high(data),
+            (0xF000, 2) => self.irq.write_control(data),
+            (0xF000, _) => self.irq.acknowledge(),
+            _ => (),
+        }
+    }
+
+    fn chr_read_addr(&self, address: u16, cartridge_addr: &mut usize) -> bool {
+        if address < 0x2000 {
+            let bank = self.chr_registers[(address >> 10) as usize] >> self.chr_shift;
+            *cartridge_addr = bank * CHR_BANK_SIZE + (address & 0x03FF) as usize;
+            return true;
+        }
+        false
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+
+    fn clock(&mut self) {
+        self.irq.clock();
+    }
+
+    fn irq_pending(&self) -> bool {
+        self.irq.pending()
+    }
+
+    fn irq_acknowledge(&mut self) {
+        self.irq.acknowledge();
+    }
+}
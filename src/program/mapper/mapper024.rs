@@ -0,0 +1,133 @@
+use super::Mapper;
+use super::vrc_irq::VrcIrq;
+use super::vrc6_audio::Vrc6Audio;
+use crate::program::Mirroring;
+
+const PRG_BANK_SIZE: usize = 8192;
+const CHR_BANK_SIZE: usize = 1024;
+
+// Konami VRC6 (mappers 24 and 26, the latter has the two register select lines swapped): a 16kb and an
+// 8kb switchable prg bank, eight chr registers, the irq counter and the expansion sound
+pub struct Mapper024 {
+    prg_banks: usize,
+    swapped_lines: bool,
+
+    prg_16k: usize,
+    prg_8k: usize,
+    chr_registers: [usize; 8],
+    // W.NN MMPP of 0xB003
+    banking: u8,
+    irq: VrcIrq,
+    audio: Vrc6Audio,
+}
+
+impl Mapper024 {
+    pub fn new(prg_amount: usize, swapped_lines: bool) -> Self {
+        Mapper024 {
+            prg_banks: prg_amount * 2,
+            swapped_lines,
+
+            prg_16k: 0,
+            prg_8k: 0,
+            chr_registers: [0; 8],
+            banking: 0,
+            irq: VrcIrq::new(),
+            audio: Vrc6Audio::new(),
+        }
+    }
+
+    fn register_select(&self, address: u16) -> u16 {
+        match self.swapped_lines {
+            true  => (address & 0x01) << 1 | (address & 0x02) >> 1,
+            false => address & 0x03,
+        }
+    }
+
+    fn chr_bank(&self, address: u16) -> usize {
+        let slot = (address >> 10) as usize;
+        // in the 2kb modes the lowest bank bit comes from the ppu a10
+        let a10 = slot & 0x01;
+        match self.banking & 0x03 {
+            0 => self.chr_registers[slot],
+            1 => (self.chr_registers[slot >> 1] & !0x01) | a10,
+            _ if slot < 4 => self.chr_registers[slot],
+            _ => (self.chr_registers[4 + ((slot - 4) >> 1)] & !0x01) | a10,
+        }
+    }
+}
+
+impl Mapper for Mapper024 {
+    fn prg_read_addr(&self, address: u16, cartridge_addr: &mut usize) -> bool {
+        let bank = match address {
+            0x8000..=0xBFFF => self.prg_16k * 2 + ((address >> 13) & 0x01) as usize,
+            0xC000..=0xDFFF => self.prg_8k,
+            0xE000..=0xFFFF => self.prg_banks - 1,
+            _ => return false,
+        };
+        *cartridge_addr = (bank % self.prg_banks) * PRG_BANK_SIZE + (address & 0x1FFF) as usize;
+        true
+    }
+
+    fn prg_write_addr(&mut self, address: u16, data: u8) {
+        if address < 0x8000 {
+            return;
+        }
+        let register = (address & 0xF000) | self.register_select(address);
+        match register {
+            0x8000..=0x8003 => self.prg_16k = (data & 0x0F) as usize,
+            0xB003 => self.banking = data,
+            0x9000..=0xB002 => self.audio.write_register(register, data),
+            0xC000..=0xC003 => self.prg_8k = (data & 0x1F) as usize,
+            0xD000..=0xD003 => self.chr_registers[(register & 0x03) as usize] = data as usize,
+            0xE000..=0xE003 => self.chr_registers[4 + (register & 0x03) as usize] = data as usize,
+            0xF000 => self.irq.set_latch(data),
+            0xF001 => self.irq.write_control(data),
+            0xF002 => self.irq.acknowledge(),
+            _ => (),
+        }
+    }
+
+    fn chr_read_addr(&self, address: u16, cartridge_addr: &mut usize) -> bool {
+        if address < 0x2000 {
+            *cartridge_addr = self.chr_bank(address) * CHR_BANK_SIZE + (address & 0x03FF) as usize;
+            return true;
+        }
+        false
+    }
+
+    fn prg_ram_addr(&self, address: u16, cartridge_addr: &mut usize) -> bool {
+        if address >= 0x6000 && address < 0x8000 && self.banking & 0x80 != 0 {
+            *cartridge_addr = (address - 0x6000) as usize;
+            return true;
+        }
+        false
+    }
+
+    // only the mirroring modes used by the games are supported (nametables in the internal vram),
+    // the chr rom nametables of the other ppu banking modes are not
+    fn mirroring(&self) -> Mirroring {
+        match (self.banking >> 2) & 0x03 {
+            0 => Mirroring::VERTICAL,
+            1 => Mirroring::HORISONTAL,
+            2 => Mirroring::SINGLE_SCREEN_LOW,
+            _ => Mirroring::SINGLE_SCREEN_HIGH,
+        }
+    }
+
+    fn clock(&mut self) {
+        self.irq.clock();
+        self.audio.clock();
+    }
+
+    fn irq_pending(&self) -> bool {
+        self.irq.pending()
+    }
+
+    fn irq_acknowledge(&mut self) {
+        self.irq.acknowledge();
+    }
+
+    fn audio_output(&self) -> f32 {
+        self.audio.output()
+    }
+}
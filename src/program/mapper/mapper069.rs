@@ -0,0 +1,139 @@
+use super::Mapper;
+use super::sunsoft5b_audio::Sunsoft5bAudio;
+use crate::program::Mirroring;
+
+const PRG_BANK_SIZE: usize = 8192;
+const CHR_BANK_SIZE: usize = 1024;
+
+// Sunsoft FME-7 / 5B: registers are written through a command (0x8000) and parameter (0xA000) pair.
+// four switchable 8kb prg banks (the one at 0x6000 can be rom or ram), eight 1kb chr banks and a 16 bit
+// irq counter decremented every cpu cycle. the 5B variant adds the expansion sound at 0xC000 / 0xE000
+pub struct Mapper069 {
+    prg_banks: usize,
+
+    command: u8,
+    chr_registers: [usize; 8],
+    // ER.. .... of command 8: ram enable and ram select of 0x6000 - 0x7FFF
+    prg_6000: u8,
+    prg_registers: [usize; 3],
+    mirroring: Mirroring,
+
+    irq_enable: bool,
+    irq_counter_enable: bool,
+    irq_counter: u16,
+    irq_pending: bool,
+    audio: Sunsoft5bAudio,
+}
+
+impl Mapper069 {
+    pub fn new(prg_amount: usize) -> Self {
+        Mapper069 {
+            prg_banks: prg_amount * 2,
+
+            command: 0,
+            chr_registers: [0; 8],
+            prg_6000: 0,
+            prg_registers: [0, 1, 2],
+            mirroring: Mirroring::VERTICAL,
+
+            irq_enable: false,
+            irq_counter_enable: false,
+            irq_counter: 0,
+            irq_pending: false,
+            audio: Sunsoft5bAudio::new(),
+        }
+    }
+
+    fn ram_selected(&self) -> bool {
+        self.prg_6000 & 0x40 != 0
+    }
+
+    fn write_parameter(&mut self, data: u8) {
+        match self.command {
+            0x00..=0x07 => self.chr_registers[self.command as usize] = data as usize,
+            0x08 => self.prg_6000 = data,
+            0x09..=0x0B => self.prg_registers[(self.command - 0x09) as usize] = (data & 0x3F) as usize,
+            0x0C => {
+                self.mirroring = match data & 0x03 {
+                    0 => Mirroring::VERTICAL,
+                    1 => Mirroring::HORISONTAL,
+                    2 => Mirroring::SINGLE_SCREEN_LOW,
+                    _ => Mirroring::SINGLE_SCREEN_HIGH,
+                };
+            },
+            0x0D => { // C--- ---T
+                self.irq_enable = data & 0x01 != 0;
+                self.irq_counter_enable = data & 0x80 != 0;
+                self.irq_pending = false;
+            },
+            0x0E => self.irq_counter = (self.irq_counter & 0xFF00) | data as u16,
+            _ => self.irq_counter = (self.irq_counter & 0x00FF) | (data as u16) << 8,
+        }
+    }
+}
+
+impl Mapper for Mapper069 {
+    fn prg_read_addr(&self, address: u16, cartridge_addr: &mut usize) -> bool {
+        let bank = match address {
+            0x6000..=0x7FFF if !self.ram_selected() => self.prg_6000 as usize & 0x3F,
+            0x8000..=0xDFFF => self.prg_registers[((address - 0x8000) >> 13) as usize],
+            0xE000..=0xFFFF => self.prg_banks - 1,
+            _ => return false,
+        };
+        *cartridge_addr = (bank % self.prg_banks) * PRG_BANK_SIZE + (address & 0x1FFF) as usize;
+        true
+    }
+
+    fn prg_write_addr(&mut self, address: u16, data: u8) {
+        match address {
+            0x8000..=0x9FFF => self.command = data & 0x0F,
+            0xA000..=0xBFFF => self.write_parameter(data),
+            0xC000..=0xDFFF => self.audio.write_address(data),
+            0xE000..=0xFFFF => self.audio.write_data(data),
+            _ => (),
+        }
+    }
+
+    fn chr_read_addr(&self, address: u16, cartridge_addr: &mut usize) -> bool {
+        if address < 0x2000 {
+            let bank = self.chr_registers[(address >> 10) as usize];
+            *cartridge_addr = bank * CHR_BANK_SIZE + (address & 0x03FF) as usize;
+            return true;
+        }
+        false
+    }
+
+    fn prg_ram_addr(&self, address: u16, cartridge_addr: &mut usize) -> bool {
+        if address >= 0x6000 && address < 0x8000 && self.ram_selected() && self.prg_6000 & 0x80 != 0 {
+            *cartridge_addr = (address - 0x6000) as usize;
+            return true;
+        }
+        false
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+
+    fn clock(&mut self) {
+        if self.irq_counter_enable {
+            self.irq_counter = self.irq_counter.wrapping_sub(1);
+            if self.irq_counter == 0xFFFF && self.irq_enable {
+                self.irq_pending = true;
+            }
+        }
+        self.audio.clock();
+    }
+
+    fn irq_pending(&self) -> bool {
+        self.irq_pending
+    }
+
+    fn irq_acknowledge(&mut self) {
+        self.irq_pending = false;
+    }
+
+    fn audio_output(&self) -> f32 {
+        self.audio.output()
+    }
+}
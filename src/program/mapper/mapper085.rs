@@ -0,0 +1,129 @@
+use super::Mapper;
+use super::vrc_irq::VrcIrq;
+use super::vrc7_audio::Vrc7Audio;
+use crate::program::Mirroring;
+
+const PRG_BANK_SIZE: usize = 8192;
+const CHR_BANK_SIZE: usize = 1024;
+
+// Konami VRC7: three switchable 8kb prg banks, eight 1kb chr banks, the irq counter and the FM sound.
+// the second register of a pair is selected by A4 (VRC7a, submapper 2) or A3 (VRC7b, submapper 1)
+pub struct Mapper085 {
+    prg_banks: usize,
+    select_line: u16,
+
+    prg_registers: [usize; 3],
+    chr_registers: [usize; 8],
+    mirroring: Mirroring,
+    prg_ram_enabled: bool,
+    irq: VrcIrq,
+    audio: Vrc7Audio,
+}
+
+impl Mapper085 {
+    pub fn new(prg_amount: usize, submapper: u8) -> Self {
+        Mapper085 {
+            prg_banks: prg_amount * 2,
+            select_line: match submapper {
+                1 => 0x08,
+                2 => 0x10,
+                _ => 0x18,
+            },
+
+            prg_registers: [0, 1, 2],
+            chr_registers: [0; 8],
+            mirroring: Mirroring::VERTICAL,
+            prg_ram_enabled: false,
+            irq: VrcIrq::new(),
+            audio: Vrc7Audio::new(),
+        }
+    }
+}
+
+impl Mapper for Mapper085 {
+    fn prg_read_addr(&self, address: u16, cartridge_addr: &mut usize) -> bool {
+        if address >= 0x8000 {
+            let bank = match (address >> 13) & 0x03 {
+                3 => self.prg_banks - 1,
+                slot => self.prg_registers[slot as usize],
+            };
+            *cartridge_addr = (bank % self.prg_banks) * PRG_BANK_SIZE + (address & 0x1FFF) as usize;
+            return true;
+        }
+        false
+    }
+
+    fn prg_write_addr(&mut self, address: u16, data: u8) {
+        if address < 0x8000 {
+            return;
+        }
+        // the sound ports are decoded from A5 and A4 on both boards
+        match address & 0xF030 {
+            0x9010 => return self.audio.write_address(data),
+            0x9030 => return self.audio.write_data(data),
+            _ => (),
+        }
+        let second = (address & self.select_line != 0) as u16;
+        match (address & 0xF000) | second {
+            0x8000 => self.prg_registers[0] = (data & 0x3F) as usize,
+            0x8001 => self.prg_registers[1] = (data & 0x3F) as usize,
+            0x9000 => self.prg_registers[2] = (data & 0x3F) as usize,
+            register @ 0xA000..=0xD001 => {
+                let bank = ((register >> 12) as usize - 0x0A) * 2 + (register & 0x01) as usize;
+                self.chr_registers[bank] = data as usize;
+            },
+            0xE000 => { // RS-- --MM
+                self.mirroring = match data & 0x03 {
+                    0 => Mirroring::VERTICAL,
+                    1 => Mirroring::HORISONTAL,
+                    2 => Mirroring::SINGLE_SCREEN_LOW,
+                    _ => Mirroring::SINGLE_SCREEN_HIGH,
+                };
+                self.prg_ram_enabled = data & 0x80 != 0;
+                self.audio.set_silenced(data & 0x40 != 0);
+            },
+            0xE001 => self.irq.set_latch(data),
+            0xF000 => self.irq.write_control(data),
+            0xF001 => self.irq.acknowledge(),
+            _ => (),
+        }
+    }
+
+    fn chr_read_addr(&self, address: u16, cartridge_addr: &mut usize) -> bool {
+        if address < 0x2000 {
+            let bank = self.chr_registers[(address >> 10) as usize];
+            *cartridge_addr = bank * CHR_BANK_SIZE + (address & 0x03FF) as usize;
+            return true;
+        }
+        false
+    }
+
+    fn prg_ram_addr(&self, address: u16, cartridge_addr: &mut usize) -> bool {
+        if address >= 0x6000 && address < 0x8000 && self.prg_ram_enabled {
+            *cartridge_addr = (address - 0x6000) as usize;
+            return true;
+        }
+        false
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+
+    fn clock(&mut self) {
+        self.irq.clock();
+        self.audio.clock();
+    }
+
+    fn irq_pending(&self) -> bool {
+        self.irq.pending()
+    }
+
+    fn irq_acknowledge(&mut self) {
+        self.irq.acknowledge();
+    }
+
+    fn audio_output(&self) -> f32 {
+        self.audio.output()
+    }
+}
@@ -0,0 +1,86 @@
+use crate::apu::pulse::Pulse;
+
+// sound of the MMC5: two pulse channels like the ones of the apu (without sweep) and an 8 bit pcm channel.
+// envelopes and length counters are clocked at a fixed rate of 240 Hz instead of the frame counter
+
+const FRAME_PERIOD: u32 = 7457; // cpu cycles
+
+pub struct Mmc5Audio {
+    pulse_1: Pulse,
+    pulse_2: Pulse,
+    pcm: u8,
+    frame_counter: u32,
+    odd_cycle: bool,
+}
+
+impl Mmc5Audio {
+    pub fn new() -> Mmc5Audio {
+        Mmc5Audio {
+            pulse_1: Pulse::without_sweep(),
+            pulse_2: Pulse::without_sweep(),
+            pcm: 0,
+            frame_counter: 0,
+            odd_cycle: false,
+        }
+    }
+
+    // 0x5000 - 0x5015
+    pub fn write_register(&mut self, address: u16, data: u8) {
+        match address {
+            0x5000..=0x5003 => self.pulse_1.write_register(address & 0x03, data),
+            0x5004..=0x5007 => self.pulse_2.write_register(address & 0x03, data),
+            // only the write mode of the pcm channel is supported (0x5010 selects the read mode
+            // which samples rom reads), its irq comes only in the read mode
+            0x5011 => {
+                if data != 0 {
+                    self.pcm = data;
+                }
+            },
+            0x5015 => {
+                self.pulse_1.set_enabled(data & 0x01 != 0);
+                self.pulse_2.set_enabled(data & 0x02 != 0);
+            },
+            _ => (),
+        }
+    }
+
+    pub fn read_register(&self, address: u16) -> Option<u8> {
+        match address {
+            0x5010 => Some(0),
+            0x5015 => Some(self.pulse_1.active() as u8 | (self.pulse_2.active() as u8) << 1),
+            _ => None,
+        }
+    }
+
+    pub fn clock(&mut self) { // every cpu cycle
+        if self.odd_cycle {
+            self.pulse_1.clock_timer();
+            self.pulse_2.clock_timer();
+        }
+        self.odd_cycle = !self.odd_cycle;
+
+        self.frame_counter += 1;
+        if self.frame_counter == FRAME_PERIOD {
+            self.frame_counter = 0;
+            for pulse in [&mut self.pulse_1, &mut self.pulse_2].iter_mut() {
+                pulse.clock_quarter_frame();
+                pulse.clock_half_frame();
+            }
+        }
+    }
+
+    // mixed like the channels of the apu
+    pub fn output(&self) -> f32 {
+        let pulse = (self.pulse_1.output() + self.pulse_2.output()) as f32;
+        let pulse_out = match pulse > 0.0 {
+            true  => 95.88 / (8128.0 / pulse + 100.0),
+            false => 0.0,
+        };
+        let pcm = self.pcm as f32 / 2.0 / 22638.0;
+        let pcm_out = match pcm > 0.0 {
+            true  => 159.79 / (1.0 / pcm + 100.0),
+            false => 0.0,
+        };
+        pulse_out + pcm_out
+    }
+}
@@ -1,12 +1,140 @@
+use std::any::Any;
 use super::Mirroring;
 
 pub mod mapper000;
 pub mod mapper001;
+pub mod mapper002;
+pub mod mapper003;
+pub mod mapper004;
+pub mod mapper005;
+pub mod mapper007;
+pub mod mapper009;
+pub mod mapper010;
+pub mod mapper019;
+pub mod mapper020;
+pub mod mapper021;
+pub mod mapper024;
+pub mod mapper069;
+pub mod mapper085;
+pub mod nsf;
+mod fds_audio;
+mod mmc5_audio;
+mod n163_audio;
+mod sunsoft5b_audio;
+mod vrc_irq;
+mod vrc6_audio;
+mod vrc7_audio;
 
-pub trait Mapper {
+// where a nametable address (0x2000 - 0x2FFF) is mapped to
+pub enum Nametable {
+    Ciram(usize),  // offset in the 2kb nametable memory of the console
+    Vram(usize),   // offset in the additional nametable memory of the cartridge
+    Chr(usize),    // offset in chr memory
+    Mapper,        // the mapper handles the access itself (ExRAM, fill mode)
+    Unmapped,
+}
+
+pub fn mirrored_nametable(mirroring: Mirroring, address: u16) -> Nametable {
+    let address = (address & 0x0FFF) as usize;
+    let offset = address & 0x03FF;
+    let table = address >> 10;
+    match mirroring {
+        Mirroring::HORISONTAL => Nametable::Ciram(((table >> 1) << 10) | offset),
+        Mirroring::VERTICAL => Nametable::Ciram(((table & 0x01) << 10) | offset),
+        Mirroring::SINGLE_SCREEN_LOW => Nametable::Ciram(offset),
+        Mirroring::SINGLE_SCREEN_HIGH => Nametable::Ciram(0x0400 | offset),
+        Mirroring::FOUR_SCREEN => match table {
+            0 | 1 => Nametable::Ciram(address & 0x07FF),
+            _ => Nametable::Vram(address & 0x07FF),
+        },
+        Mirroring::UNDEFINED => Nametable::Unmapped,
+    }
+}
+
+// lets the cartridge reach the controls of a specific mapper (disk drive, nsf player) which are not part
+// of the bus mapping
+pub trait AsAny {
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+impl<T: Any> AsAny for T {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+pub trait Mapper: AsAny {
     fn prg_read_addr(&self, address: u16, cartridge_addr: &mut usize) -> bool;
     fn prg_write_addr(&mut self, address: u16, data: u8);
+
+    // registers which are read by the cpu (with side effects), returns false for other addresses
+    fn prg_read_register(&mut self, _address: u16, _data: &mut u8) -> bool {
+        false
+    }
     fn chr_read_addr(&self, address: u16, cartridge_addr: &mut usize) -> bool;
-    fn chr_write_addr(&mut self, address: u16, data: u8);
     fn mirroring(&self) -> Mirroring;
+
+    // chr ram writes go through the same bank translation as reads
+    fn chr_write_addr(&self, address: u16, cartridge_addr: &mut usize) -> bool {
+        self.chr_read_addr(address, cartridge_addr)
+    }
+
+    // prg ram is placed at 0x6000 - 0x7FFF on most boards
+    fn prg_ram_addr(&self, address: u16, cartridge_addr: &mut usize) -> bool {
+        if address >= 0x6000 && address < 0x8000 {
+            *cartridge_addr = (address & 0x1FFF) as usize;
+            return true;
+        }
+        false
+    }
+
+    fn prg_ram_writable(&self) -> bool {
+        true
+    }
+
+    // mappers can take over nametables instead of the fixed mirroring
+    fn nametable_addr(&self, address: u16) -> Nametable {
+        mirrored_nametable(self.mirroring(), address)
+    }
+
+    // accesses to nametables mapped to Nametable::Mapper
+    fn read_nametable(&self, _address: u16) -> u8 {
+        0
+    }
+
+    fn write_nametable(&mut self, _address: u16, _data: u8) {}
+
+    // on boards with bus conflicts a written value is ANDed with the rom byte at the same address
+    fn bus_conflicts(&self) -> bool {
+        false
+    }
+
+    // called once per cpu cycle
+    fn clock(&mut self) {}
+
+    // called for every address the ppu puts on its bus (pattern tables and nametables)
+    fn ppu_address(&mut self, _address: u16) {}
+
+    // called after the ppu has read pattern data, the read itself isn't affected by changes made here
+    fn ppu_read(&mut self, _address: u16) {}
+
+    // writes of the cpu to the ppu registers (0x2000 - 0x2007), the MMC5 watches the sprite size and rendering
+    fn ppu_register_write(&mut self, _address: u16, _data: u8) {}
+
+    // level of the cartridge irq line
+    fn irq_pending(&self) -> bool {
+        false
+    }
+
+    fn irq_acknowledge(&mut self) {}
+
+    // expansion sound, in units of the apu mixer output (a pulse channel at full volume is about 0.15)
+    fn audio_output(&self) -> f32 {
+        0.0
+    }
 }
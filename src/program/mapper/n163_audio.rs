@@ -0,0 +1,106 @@
+// sound of the Namco 163: up to 8 wavetable channels which live in the 128 byte internal ram, together
+// with the 4 bit samples of their waves. the chip updates one channel every 15 cpu cycles and outputs
+// the channels in turn, this is mixed as the average of the active channels
+
+const UPDATE_PERIOD: u8 = 15; // cpu cycles
+const RAM_SIZE: usize = 0x80;
+const CHANNEL_SCALE: f32 = 0.0015;
+
+pub struct N163Audio {
+    ram: [u8; RAM_SIZE],
+    // ram address with auto increment in bit 7
+    address: u8,
+    divider: u8,
+    // channel updated next, 7 is the one at 0x78 - 0x7F which is always active
+    channel: usize,
+    outputs: [i16; 8],
+}
+
+impl N163Audio {
+    pub fn new() -> N163Audio {
+        N163Audio {
+            ram: [0; RAM_SIZE],
+            address: 0,
+            divider: 0,
+            channel: 7,
+            outputs: [0; 8],
+        }
+    }
+
+    // 0xF800 - 0xFFFF
+    pub fn write_address(&mut self, data: u8) {
+        self.address = data;
+    }
+
+    // 0x4800 - 0x4FFF
+    pub fn write_data(&mut self, data: u8) {
+        self.ram[(self.address & 0x7F) as usize] = data;
+        self.increment_address();
+    }
+
+    pub fn read_data(&mut self) -> u8 {
+        let data = self.ram[(self.address & 0x7F) as usize];
+        self.increment_address();
+        data
+    }
+
+    fn increment_address(&mut self) {
+        if self.address & 0x80 != 0 {
+            self.address = 0x80 | (self.address.wrapping_add(1) & 0x7F);
+        }
+    }
+
+    fn active_channels(&self) -> usize {
+        ((self.ram[0x7F] >> 4) & 0x07) as usize + 1
+    }
+
+    fn sample(&self, position: usize) -> i16 {
+        let byte = self.ram[(position >> 1) & (RAM_SIZE - 1)];
+        let sample = match position & 0x01 {
+            0 => byte & 0x0F,
+            _ => byte >> 4,
+        };
+        sample as i16 - 8
+    }
+
+    fn update_channel(&mut self, channel: usize) {
+        // FFFF FFFF  PPPP PPPP  FFFF FFFF  PPPP PPPP  LLLL LLFF  PPPP PPPP  AAAA AAAA  ---- VVVV
+        let base = 0x40 + channel * 8;
+        let registers = &self.ram[base..base + 8];
+        let frequency = registers[0] as u32 | (registers[2] as u32) << 8 | ((registers[4] & 0x03) as u32) << 16;
+        let phase = registers[1] as u32 | (registers[3] as u32) << 8 | (registers[5] as u32) << 16;
+        let length = 256 - (registers[4] & 0xFC) as u32;
+        let wave_address = registers[6] as usize;
+        let volume = (registers[7] & 0x0F) as i16;
+
+        let phase = (phase + frequency) % (length << 16);
+        self.ram[base + 1] = phase as u8;
+        self.ram[base + 3] = (phase >> 8) as u8;
+        self.ram[base + 5] = (phase >> 16) as u8;
+        self.outputs[channel] = self.sample(wave_address + (phase >> 16) as usize) * volume;
+    }
+
+    pub fn clock(&mut self) { // every cpu cycle
+        self.divider += 1;
+        if self.divider < UPDATE_PERIOD {
+            return;
+        }
+        self.divider = 0;
+
+        let first = 8 - self.active_channels();
+        if self.channel < first {
+            self.channel = 7;
+        }
+        self.update_channel(self.channel);
+        self.channel = match self.channel {
+            channel if channel == first => 7,
+            channel => channel - 1,
+        };
+    }
+
+    pub fn output(&self) -> f32 {
+        let first = 8 - self.active_channels();
+        let sum: i16 = self.outputs[first..].iter().sum();
+        sum as f32 / (8 - first) as f32 * CHANNEL_SCALE
+    }
+}
@@ -0,0 +1,388 @@
+use super::Mapper;
+use super::fds_audio::FdsAudio;
+use super::mmc5_audio::Mmc5Audio;
+use super::n163_audio::N163Audio;
+use super::sunsoft5b_audio::Sunsoft5bAudio;
+use super::vrc6_audio::Vrc6Audio;
+use super::vrc7_audio::Vrc7Audio;
+use crate::apu::CPU_FREQUENCY;
+use crate::program::Mirroring;
+use crate::program::nsf::{self, NsfHeader};
+
+// nsf "board": 4kb banks of the tune at 0x8000 - 0xFFFF (switched by 0x5FF8 - 0x5FFF), 8kb of ram
+// at 0x6000 and a small driver which calls INIT and then PLAY on every irq of a timer.
+// tunes for the disk system have ram in the whole 0x6000 - 0xFFFF range, banks are copied into it
+
+const BANK_SIZE: usize = 0x1000;
+const DRIVER_ADDRESS: u16 = 0x4100;
+const RESET_ADDRESS: u16 = 0x4100;
+const IRQ_ADDRESS: u16 = 0x4148;
+const NMI_ADDRESS: u16 = 0x4150;
+// driver registers: song, region, irq acknowledge (reads) and init (writes)
+const SONG_REGISTER: u16 = 0x41F0;
+const REGION_REGISTER: u16 = 0x41F1;
+const IRQ_REGISTER: u16 = 0x41F2;
+const INIT_REGISTER: u16 = 0x41F3;
+
+pub struct NsfMapper {
+    data: Vec<u8>,
+    ram: Vec<u8>,
+    fds: bool,
+    init_banks: [usize; 10],
+    banks: [usize; 8],
+    driver: Vec<u8>,
+    song: usize,
+    pal: bool,
+
+    play_period: u32,
+    play_counter: u32,
+    play_irq: bool,
+
+    fds_audio: Option<FdsAudio>,
+    // MMC5 tunes also use its ExRAM as work ram and the multiplier
+    mmc5_audio: Option<Mmc5Audio>,
+    exram: Vec<u8>,
+    multiplicand: u8,
+    multiplier: u8,
+    vrc6_audio: Option<Vrc6Audio>,
+    vrc7_audio: Option<Vrc7Audio>,
+    n163_audio: Option<N163Audio>,
+    sunsoft5b_audio: Option<Sunsoft5bAudio>,
+}
+
+impl NsfMapper {
+    pub fn new(header: &NsfHeader, program: &[u8]) -> Self {
+        let fds = header.has_expansion(nsf::FDS);
+        // program data is aligned to the banks, tunes without bankswitching are loaded by their address
+        let (data, init_banks) = match header.bankswitched() {
+            true => {
+                let mut data = vec![0; (header.load_address as usize) & 0x0FFF];
+                data.extend_from_slice(program);
+                let mut init_banks = [0; 10];
+                for (slot, bank) in header.bankswitch.iter().enumerate() {
+                    init_banks[slot + 2] = *bank as usize;
+                }
+                // ram of the disk system at 0x6000 - 0x7FFF takes the banks of 0xE000 - 0xFFFF
+                init_banks[0] = init_banks[8];
+                init_banks[1] = init_banks[9];
+                (data, init_banks)
+            },
+            false => {
+                let start = (header.load_address as usize).max(0x6000) - 0x6000;
+                let mut data = vec![0; 0xA000];
+                let size = program.len().min(data.len() - start);
+                data[start..start + size].copy_from_slice(&program[..size]);
+                let mut init_banks = [0; 10];
+                (0..10).for_each(|slot| init_banks[slot] = slot);
+                (data, init_banks)
+            },
+        };
+        let mmc5 = header.has_expansion(nsf::MMC5);
+        let vrc6 = header.has_expansion(nsf::VRC6);
+        let vrc7 = header.has_expansion(nsf::VRC7);
+        let n163 = header.has_expansion(nsf::N163);
+        let sunsoft5b = header.has_expansion(nsf::SUNSOFT_5B);
+        let play_period = (header.play_speed() as f64 * CPU_FREQUENCY / 1_000_000.0).round() as u32;
+
+        let mut mapper = NsfMapper {
+            data,
+            ram: vec![0; if fds { 0xA000 } else { 0x2000 }],
+            fds,
+            init_banks,
+            banks: [0; 8],
+            driver: NsfMapper::driver(header.init_address, header.play_address),
+            song: header.starting_song,
+            pal: header.pal,
+
+            play_period,
+            play_counter: play_period,
+            play_irq: false,
+
+            fds_audio: if fds { Some(FdsAudio::new()) } else { None },
+            mmc5_audio: if mmc5 { Some(Mmc5Audio::new()) } else { None },
+            exram: vec![0; 0x400],
+            multiplicand: 0xFF,
+            multiplier: 0xFF,
+            vrc6_audio: if vrc6 { Some(Vrc6Audio::new()) } else { None },
+            vrc7_audio: if vrc7 { Some(Vrc7Audio::new()) } else { None },
+            n163_audio: if n163 { Some(N163Audio::new()) } else { None },
+            sunsoft5b_audio: if sunsoft5b { Some(Sunsoft5bAudio::new()) } else { None },
+        };
+        mapper.init();
+        mapper
+    }
+
+    // resets the tune state before INIT is called
+    fn init(&mut self) {
+        self.ram.iter_mut().for_each(|byte| *byte = 0);
+        for slot in 0..10 {
+            if self.fds || slot >= 2 {
+                self.switch_bank(slot, self.init_banks[slot]);
+            }
+        }
+        self.play_counter = self.play_period;
+        self.play_irq = false;
+        if self.fds {
+            self.fds_audio = Some(FdsAudio::new());
+        }
+        if self.mmc5_audio.is_some() {
+            self.mmc5_audio = Some(Mmc5Audio::new());
+        }
+        self.exram.iter_mut().for_each(|byte| *byte = 0);
+        if self.vrc6_audio.is_some() {
+            self.vrc6_audio = Some(Vrc6Audio::new());
+        }
+        if self.vrc7_audio.is_some() {
+            self.vrc7_audio = Some(Vrc7Audio::new());
+        }
+        if self.n163_audio.is_some() {
+            self.n163_audio = Some(N163Audio::new());
+        }
+        if self.sunsoft5b_audio.is_some() {
+            self.sunsoft5b_audio = Some(Sunsoft5bAudio::new());
+        }
+    }
+
+    // slot 0 is 0x6000, slot 9 is 0xF000
+    fn switch_bank(&mut self, slot: usize, bank: usize) {
+        match self.fds {
+            true => {
+                let ram = &mut self.ram[slot * BANK_SIZE..(slot + 1) * BANK_SIZE];
+                for (offset, byte) in ram.iter_mut().enumerate() {
+                    *byte = self.data.get(bank * BANK_SIZE + offset).copied().unwrap_or(0);
+                }
+            },
+            false => if slot >= 2 {
+                self.banks[slot - 2] = bank;
+            },
+        }
+    }
+
+    fn read(&self, address: u16) -> u8 {
+        match address {
+            0x6000..=0xFFFF if self.fds => self.ram[(address - 0x6000) as usize],
+            0x6000..=0x7FFF => self.ram[(address & 0x1FFF) as usize],
+            0x8000..=0xFFFF => {
+                let bank = self.banks[((address - 0x8000) as usize) / BANK_SIZE];
+                self.data.get(bank * BANK_SIZE + (address as usize & 0x0FFF)).copied().unwrap_or(0)
+            },
+            _ => 0,
+        }
+    }
+
+    // RESET: clears ram, initializes the apu, calls INIT with the song in A and the region in X
+    // and waits for irqs. IRQ: calls PLAY if the irq came from the play timer
+    fn driver(init: u16, play: u16) -> Vec<u8> {
+        let [init_low, init_high] = init.to_le_bytes();
+        let [play_low, play_high] = play.to_le_bytes();
+        let mut driver = vec![
+            0x78,             // 4100 SEI
+            0xD8,             // 4101 CLD
+            0xA2, 0xFF,       // 4102 LDX #$FF
+            0x9A,             // 4104 TXS
+            0xA9, 0x00,       // 4105 LDA #$00
+            0xAA,             // 4107 TAX
+            0x95, 0x00,       // 4108 STA $00,X
+            0x9D, 0x00, 0x01, // 410A STA $0100,X
+            0x9D, 0x00, 0x02, // 410D STA $0200,X
+            0x9D, 0x00, 0x03, // 4110 STA $0300,X
+            0x9D, 0x00, 0x04, // 4113 STA $0400,X
+            0x9D, 0x00, 0x05, // 4116 STA $0500,X
+            0x9D, 0x00, 0x06, // 4119 STA $0600,X
+            0x9D, 0x00, 0x07, // 411C STA $0700,X
+            0xE8,             // 411F INX
+            0xD0, 0xE6,       // 4120 BNE $4108
+            0x8D, 0xF3, 0x41, // 4122 STA $41F3
+            0xA2, 0x13,       // 4125 LDX #$13
+            0x9D, 0x00, 0x40, // 4127 STA $4000,X
+            0xCA,             // 412A DEX
+            0x10, 0xFA,       // 412B BPL $4127
+            0x8D, 0x15, 0x40, // 412D STA $4015
+            0xA9, 0x0F,       // 4130 LDA #$0F
+            0x8D, 0x15, 0x40, // 4132 STA $4015
+            0xA9, 0x40,       // 4135 LDA #$40
+            0x8D, 0x17, 0x40, // 4137 STA $4017
+            0xAD, 0xF1, 0x41, // 413A LDA $41F1
+            0xAA,             // 413D TAX
+            0xAD, 0xF0, 0x41, // 413E LDA $41F0
+            0x20, init_low, init_high, // 4141 JSR INIT
+            0x58,             // 4144 CLI
+            0x4C, 0x45, 0x41, // 4145 JMP $4145
+            0xAD, 0xF2, 0x41, // 4148 LDA $41F2
+            0xF0, 0x03,       // 414B BEQ $4150
+            0x20, play_low, play_high, // 414D JSR PLAY
+            0x40,             // 4150 RTI
+        ];
+        driver.resize(0x100, 0);
+        driver
+    }
+
+    // the song which is played after the next reset
+    pub fn track(&self) -> usize {
+        self.song
+    }
+
+    // the driver is restarted by a cpu reset
+    pub fn select_track(&mut self, track: usize) {
+        self.song = track;
+    }
+}
+
+impl Mapper for NsfMapper {
+    // all reads are handled by prg_read_register
+    fn prg_read_addr(&self, _address: u16, _cartridge_addr: &mut usize) -> bool {
+        false
+    }
+
+    fn prg_write_addr(&mut self, address: u16, data: u8) {
+        match address {
+            INIT_REGISTER => self.init(),
+            0x4040..=0x408A => {
+                if let Some(fds_audio) = self.fds_audio.as_mut() {
+                    fds_audio.write_register(address, data);
+                }
+            },
+            0x5000..=0x5015 => {
+                if let Some(mmc5_audio) = self.mmc5_audio.as_mut() {
+                    mmc5_audio.write_register(address, data);
+                }
+            },
+            0x5205 => self.multiplicand = data,
+            0x5206 => self.multiplier = data,
+            0x5C00..=0x5FF5 => self.exram[(address - 0x5C00) as usize] = data,
+            0x5FF6..=0x5FFF => self.switch_bank((address - 0x5FF6) as usize, data as usize),
+            0x9000..=0x9003 | 0xA000..=0xA002 | 0xB000..=0xB002 if self.vrc6_audio.is_some() => {
+                if let Some(vrc6_audio) = self.vrc6_audio.as_mut() {
+                    vrc6_audio.write_register(address, data);
+                }
+            },
+            0x9010 | 0x9030 if self.vrc7_audio.is_some() => {
+                if let Some(vrc7_audio) = self.vrc7_audio.as_mut() {
+                    match address {
+                        0x9010 => vrc7_audio.write_address(data),
+                        _ => vrc7_audio.write_data(data),
+                    }
+                }
+            },
+            0x4800 | 0xF800 if self.n163_audio.is_some() => {
+                if let Some(n163_audio) = self.n163_audio.as_mut() {
+                    match address {
+                        0x4800 => n163_audio.write_data(data),
+                        _ => n163_audio.write_address(data),
+                    }
+                }
+            },
+            0xC000 | 0xE000 if self.sunsoft5b_audio.is_some() => {
+                if let Some(sunsoft5b_audio) = self.sunsoft5b_audio.as_mut() {
+                    match address {
+                        0xC000 => sunsoft5b_audio.write_address(data),
+                        _ => sunsoft5b_audio.write_data(data),
+                    }
+                }
+            },
+            0x6000..=0xFFFF if self.fds => self.ram[(address - 0x6000) as usize] = data,
+            0x6000..=0x7FFF => self.ram[(address & 0x1FFF) as usize] = data,
+            _ => (),
+        }
+    }
+
+    fn prg_read_register(&mut self, address: u16, data: &mut u8) -> bool {
+        *data = match address {
+            SONG_REGISTER => self.song as u8,
+            REGION_REGISTER => self.pal as u8,
+            IRQ_REGISTER => {
+                let irq = self.play_irq;
+                self.play_irq = false;
+                irq as u8
+            },
+            0x4100..=0x41FF => self.driver[(address - DRIVER_ADDRESS) as usize],
+            0x4040..=0x4097 => match self.fds_audio.as_ref() {
+                Some(fds_audio) => fds_audio.read_register(address),
+                None => return false,
+            },
+            0x4800 if self.n163_audio.is_some() => match self.n163_audio.as_mut() {
+                Some(n163_audio) => n163_audio.read_data(),
+                None => return false,
+            },
+            0x5010 | 0x5015 => match self.mmc5_audio.as_ref().and_then(|audio| audio.read_register(address)) {
+                Some(value) => value,
+                None => return false,
+            },
+            0x5205 => (self.multiplicand as u16 * self.multiplier as u16) as u8,
+            0x5206 => ((self.multiplicand as u16 * self.multiplier as u16) >> 8) as u8,
+            0x5C00..=0x5FF5 => self.exram[(address - 0x5C00) as usize],
+            // vectors of the driver
+            0xFFFA => NMI_ADDRESS as u8,
+            0xFFFB => (NMI_ADDRESS >> 8) as u8,
+            0xFFFC => RESET_ADDRESS as u8,
+            0xFFFD => (RESET_ADDRESS >> 8) as u8,
+            0xFFFE => IRQ_ADDRESS as u8,
+            0xFFFF => (IRQ_ADDRESS >> 8) as u8,
+            0x6000..=0xFFFF => self.read(address),
+            _ => return false,
+        };
+        true
+    }
+
+    // the tune has no prg ram of the cartridge, the mapper keeps its own
+    fn prg_ram_addr(&self, _address: u16, _cartridge_addr: &mut usize) -> bool {
+        false
+    }
+
+    fn chr_read_addr(&self, address: u16, cartridge_addr: &mut usize) -> bool {
+        if address < 0x2000 {
+            *cartridge_addr = address as usize;
+            return true;
+        }
+        false
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        Mirroring::VERTICAL
+    }
+
+    fn clock(&mut self) {
+        if self.play_counter == 0 {
+            self.play_counter = self.play_period;
+            self.play_irq = true;
+        } else {
+            self.play_counter -= 1;
+        }
+        if let Some(fds_audio) = self.fds_audio.as_mut() {
+            fds_audio.clock();
+        }
+        if let Some(mmc5_audio) = self.mmc5_audio.as_mut() {
+            mmc5_audio.clock();
+        }
+        if let Some(vrc6_audio) = self.vrc6_audio.as_mut() {
+            vrc6_audio.clock();
+        }
+        if let Some(vrc7_audio) = self.vrc7_audio.as_mut() {
+            vrc7_audio.clock();
+        }
+        if let Some(n163_audio) = self.n163_audio.as_mut() {
+            n163_audio.clock();
+        }
+        if let Some(sunsoft5b_audio) = self.sunsoft5b_audio.as_mut() {
+            sunsoft5b_audio.clock();
+        }
+    }
+
+    fn irq_pending(&self) -> bool {
+        self.play_irq
+    }
+
+    fn irq_acknowledge(&mut self) {
+        self.play_irq = false;
+    }
+
+    fn audio_output(&self) -> f32 {
+        let fds = self.fds_audio.as_ref().map_or(0.0, |audio| audio.output());
+        let mmc5 = self.mmc5_audio.as_ref().map_or(0.0, |audio| audio.output());
+        let vrc6 = self.vrc6_audio.as_ref().map_or(0.0, |audio| audio.output());
+        let vrc7 = self.vrc7_audio.as_ref().map_or(0.0, |audio| audio.output());
+        let n163 = self.n163_audio.as_ref().map_or(0.0, |audio| audio.output());
+        let sunsoft5b = self.sunsoft5b_audio.as_ref().map_or(0.0, |audio| audio.output());
+        fds + mmc5 + vrc6 + vrc7 + n163 + sunsoft5b
+    }
+}
@@ -0,0 +1,199 @@
+// sound of the Sunsoft 5B: a YM2149 with three square channels, a noise generator and an envelope
+// generator. the chip runs at half the cpu clock, its timers count every 8 of its cycles
+
+const TICK_PERIOD: u8 = 16; // cpu cycles
+const CHANNEL_SCALE: f32 = 0.08;
+
+struct Tone {
+    period: u16,
+    counter: u16,
+    high: bool,
+}
+
+impl Tone {
+    fn new() -> Tone {
+        Tone {
+            period: 0,
+            counter: 0,
+            high: false,
+        }
+    }
+
+    fn tick(&mut self) {
+        self.counter += 1;
+        if self.counter >= self.period.max(1) {
+            self.counter = 0;
+            self.high = !self.high;
+        }
+    }
+}
+
+struct Envelope {
+    period: u16,
+    counter: u16,
+    // CAAH of register 0x0D: continue, attack, alternate, hold
+    shape: u8,
+    step: u8,
+    rising: bool,
+    holding: bool,
+}
+
+impl Envelope {
+    fn new() -> Envelope {
+        Envelope {
+            period: 0,
+            counter: 0,
+            shape: 0,
+            step: 0,
+            rising: false,
+            holding: false,
+        }
+    }
+
+    fn restart(&mut self, shape: u8) {
+        self.shape = shape & 0x0F;
+        self.step = 0;
+        self.counter = 0;
+        self.rising = shape & 0x04 != 0;
+        self.holding = false;
+    }
+
+    fn tick(&mut self) {
+        self.counter += 1;
+        if self.counter < self.period.max(1) {
+            return;
+        }
+        self.counter = 0;
+        if self.holding {
+            return;
+        }
+        self.step += 1;
+        if self.step < 32 {
+            return;
+        }
+        let (continues, alternate, hold) = (self.shape & 0x08 != 0, self.shape & 0x02 != 0, self.shape & 0x01 != 0);
+        if !continues {
+            // ends silent
+            self.holding = true;
+            self.rising = false;
+            self.step = 31;
+        } else if hold {
+            self.holding = true;
+            self.rising ^= alternate;
+            self.step = 31;
+        } else {
+            self.rising ^= alternate;
+            self.step = 0;
+        }
+    }
+
+    // 5 bit level
+    fn level(&self) -> u8 {
+        match self.rising {
+            true  => self.step,
+            false => 31 - self.step,
+        }
+    }
+}
+
+pub struct Sunsoft5bAudio {
+    address: u8,
+    tones: [Tone; 3],
+    volumes: [u8; 3],
+    // ---N NNTT T of register 0x07, set bits disable the tone or the noise of a channel
+    mixer: u8,
+    noise_period: u8,
+    noise_counter: u8,
+    noise_shift: u32,
+    noise_odd_tick: bool,
+    envelope: Envelope,
+    divider: u8,
+}
+
+impl Sunsoft5bAudio {
+    pub fn new() -> Sunsoft5bAudio {
+        Sunsoft5bAudio {
+            address: 0,
+            tones: [Tone::new(), Tone::new(), Tone::new()],
+            volumes: [0; 3],
+            mixer: 0xFF,
+            noise_period: 0,
+            noise_counter: 0,
+            noise_shift: 1,
+            noise_odd_tick: false,
+            envelope: Envelope::new(),
+            divider: 0,
+        }
+    }
+
+    // 0xC000 - 0xDFFF
+    pub fn write_address(&mut self, data: u8) {
+        self.address = data;
+    }
+
+    // 0xE000 - 0xFFFF
+    pub fn write_data(&mut self, data: u8) {
+        match self.address {
+            0x00 | 0x02 | 0x04 => {
+                let tone = &mut self.tones[(self.address >> 1) as usize];
+                tone.period = (tone.period & 0x0F00) | data as u16;
+            },
+            0x01 | 0x03 | 0x05 => {
+                let tone = &mut self.tones[(self.address >> 1) as usize];
+                tone.period = (tone.period & 0x00FF) | ((data & 0x0F) as u16) << 8;
+            },
+            0x06 => self.noise_period = data & 0x1F,
+            0x07 => self.mixer = data,
+            0x08..=0x0A => self.volumes[(self.address - 0x08) as usize] = data & 0x1F,
+            0x0B => self.envelope.period = (self.envelope.period & 0xFF00) | data as u16,
+            0x0C => self.envelope.period = (self.envelope.period & 0x00FF) | (data as u16) << 8,
+            0x0D => self.envelope.restart(data),
+            _ => (),
+        }
+    }
+
+    pub fn clock(&mut self) { // every cpu cycle
+        self.divider += 1;
+        if self.divider < TICK_PERIOD {
+            return;
+        }
+        self.divider = 0;
+
+        self.tones.iter_mut().for_each(|tone| tone.tick());
+        self.envelope.tick();
+        // the noise runs at half the rate of the tones
+        self.noise_odd_tick = !self.noise_odd_tick;
+        if self.noise_odd_tick {
+            self.noise_counter += 1;
+            if self.noise_counter >= self.noise_period.max(1) {
+                self.noise_counter = 0;
+                // 17 bit lfsr with taps 0 and 3
+                let feedback = (self.noise_shift ^ (self.noise_shift >> 3)) & 0x01;
+                self.noise_shift = (self.noise_shift >> 1) | feedback << 16;
+            }
+        }
+    }
+
+    pub fn output(&self) -> f32 {
+        let noise = self.noise_shift & 0x01 != 0;
+        let mut output = 0.0;
+        for (channel, tone) in self.tones.iter().enumerate() {
+            let tone_on = tone.high || self.mixer & (0x01 << channel) != 0;
+            let noise_on = noise || self.mixer & (0x08 << channel) != 0;
+            if !(tone_on && noise_on) {
+                continue;
+            }
+            // 4 bit volumes are 3 dB steps, the envelope has 1.5 dB steps
+            let volume = self.volumes[channel];
+            let level = match volume & 0x10 != 0 {
+                true  => self.envelope.level(),
+                false if volume == 0 => 0,
+                false => (volume & 0x0F) * 2 + 1,
+            };
+            if level > 0 {
+                output += 10f32.powf((level as f32 - 31.0) * 1.5 / 20.0);
+            }
+        }
+        output * CHANNEL_SCALE
+    }
+}
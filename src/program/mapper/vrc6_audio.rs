@@ -0,0 +1,178 @@
+// sound of the Konami VRC6: two pulse channels with 8 duty cycles and a sawtooth channel.
+// the channels have no envelopes or length counters, their timers are clocked every cpu cycle
+
+// output of a pulse channel at full volume is about the one of an apu pulse channel
+const OUTPUT_SCALE: f32 = 0.0075;
+
+struct Vrc6Pulse {
+    volume: u8,
+    duty: u8,
+    ignore_duty: bool,
+    period: u16,
+    enabled: bool,
+    timer: u16,
+    step: u8,
+}
+
+impl Vrc6Pulse {
+    fn new() -> Vrc6Pulse {
+        Vrc6Pulse {
+            volume: 0,
+            duty: 0,
+            ignore_duty: false,
+            period: 0,
+            enabled: false,
+            timer: 0,
+            step: 0,
+        }
+    }
+
+    fn write_register(&mut self, register: u16, data: u8) {
+        match register {
+            0 => { // MDDD VVVV
+                self.ignore_duty = data & 0x80 != 0;
+                self.duty = (data >> 4) & 0x07;
+                self.volume = data & 0x0F;
+            },
+            1 => self.period = (self.period & 0x0F00) | data as u16,
+            _ => { // E--- PPPP
+                self.period = (self.period & 0x00FF) | ((data & 0x0F) as u16) << 8;
+                self.enabled = data & 0x80 != 0;
+                if !self.enabled {
+                    self.step = 0;
+                }
+            },
+        }
+    }
+
+    fn clock(&mut self, shift: u8) {
+        if !self.enabled {
+            return;
+        }
+        if self.timer == 0 {
+            self.timer = self.period >> shift;
+            self.step = (self.step + 1) & 0x0F;
+        } else {
+            self.timer -= 1;
+        }
+    }
+
+    fn output(&self) -> u8 {
+        match self.enabled && (self.ignore_duty || self.step <= self.duty) {
+            true  => self.volume,
+            false => 0,
+        }
+    }
+}
+
+struct Vrc6Sawtooth {
+    rate: u8,
+    period: u16,
+    enabled: bool,
+    timer: u16,
+    step: u8,
+    accumulator: u8,
+}
+
+impl Vrc6Sawtooth {
+    fn new() -> Vrc6Sawtooth {
+        Vrc6Sawtooth {
+            rate: 0,
+            period: 0,
+            enabled: false,
+            timer: 0,
+            step: 0,
+            accumulator: 0,
+        }
+    }
+
+    fn write_register(&mut self, register: u16, data: u8) {
+        match register {
+            0 => self.rate = data & 0x3F,
+            1 => self.period = (self.period & 0x0F00) | data as u16,
+            _ => {
+                self.period = (self.period & 0x00FF) | ((data & 0x0F) as u16) << 8;
+                self.enabled = data & 0x80 != 0;
+                if !self.enabled {
+                    self.step = 0;
+                    self.accumulator = 0;
+                }
+            },
+        }
+    }
+
+    fn clock(&mut self, shift: u8) {
+        if !self.enabled {
+            return;
+        }
+        if self.timer > 0 {
+            self.timer -= 1;
+            return;
+        }
+        self.timer = self.period >> shift;
+        // the rate is added on every other step, the accumulator is cleared after the 7th addition
+        self.step += 1;
+        if self.step == 14 {
+            self.step = 0;
+            self.accumulator = 0;
+        } else if self.step & 0x01 == 0 {
+            self.accumulator = self.accumulator.wrapping_add(self.rate);
+        }
+    }
+
+    fn output(&self) -> u8 {
+        self.accumulator >> 3
+    }
+}
+
+pub struct Vrc6Audio {
+    pulse_1: Vrc6Pulse,
+    pulse_2: Vrc6Pulse,
+    sawtooth: Vrc6Sawtooth,
+    halt: bool,
+    shift: u8,
+}
+
+impl Vrc6Audio {
+    pub fn new() -> Vrc6Audio {
+        Vrc6Audio {
+            pulse_1: Vrc6Pulse::new(),
+            pulse_2: Vrc6Pulse::new(),
+            sawtooth: Vrc6Sawtooth::new(),
+            halt: false,
+            shift: 0,
+        }
+    }
+
+    // 0x9000 - 0x9003, 0xA000 - 0xA002, 0xB000 - 0xB002 (with the register select lines already decoded)
+    pub fn write_register(&mut self, address: u16, data: u8) {
+        match address {
+            0x9003 => { // ---- -ABH
+                self.halt = data & 0x01 != 0;
+                self.shift = match data & 0x06 {
+                    0 => 0,
+                    0x02 => 4,
+                    _ => 8,
+                };
+            },
+            0x9000..=0x9002 => self.pulse_1.write_register(address & 0x03, data),
+            0xA000..=0xA002 => self.pulse_2.write_register(address & 0x03, data),
+            0xB000..=0xB002 => self.sawtooth.write_register(address & 0x03, data),
+            _ => (),
+        }
+    }
+
+    pub fn clock(&mut self) { // every cpu cycle
+        if self.halt {
+            return;
+        }
+        self.pulse_1.clock(self.shift);
+        self.pulse_2.clock(self.shift);
+        self.sawtooth.clock(self.shift);
+    }
+
+    pub fn output(&self) -> f32 {
+        let sum = self.pulse_1.output() + self.pulse_2.output() + self.sawtooth.output();
+        sum as f32 * OUTPUT_SCALE
+    }
+}
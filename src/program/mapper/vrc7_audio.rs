@@ -0,0 +1,369 @@
+use std::f32::consts::PI;
+
+// sound of the Konami VRC7: a reduced YM2413 (OPLL) FM synthesizer with six two-operator channels,
+// 15 built-in instruments and one custom instrument. the chip makes a sample every 36 cpu cycles,
+// the envelope rates, vibrato and tremolo are approximations of the real chip in floating point
+
+const SAMPLE_PERIOD: u32 = 36; // cpu cycles
+const SAMPLE_RATE: f32 = 1789773.0 / SAMPLE_PERIOD as f32;
+const CHANNELS: usize = 6;
+const PHASE_BITS: u32 = 18;
+const PHASE_MASK: u32 = (1 << PHASE_BITS) - 1;
+
+// attenuation (dB) at which an operator is silent
+const MAX_ATTENUATION: f32 = 48.0;
+// phase shift (radians) of the carrier by a modulator at full volume
+const MODULATION_DEPTH: f32 = 4.0 * PI;
+const CHANNEL_SCALE: f32 = 0.06;
+
+// tremolo: 4.8 dB at 3.7 Hz, vibrato: 8 steps of 1024 samples (about 6 Hz)
+const AM_DEPTH: f32 = 4.8;
+const AM_PERIOD: u32 = 13432;
+const PM_STEPS: [i32; 8] = [0, 1, 2, 1, 0, -1, -2, -1];
+
+// frequency multipliers times two
+const MULTIPLIERS: [u32; 16] = [1, 2, 4, 6, 8, 10, 12, 14, 16, 18, 20, 20, 24, 24, 30, 30];
+const KEY_SCALE_LEVELS: [f32; 16] = [
+    0.0, 18.0, 24.0, 27.75, 30.0, 32.25, 33.75, 35.25,
+    36.0, 37.5, 38.25, 39.0, 39.75, 40.5, 41.25, 42.0,
+];
+
+// the built-in instruments 1 - 15
+const INSTRUMENTS: [[u8; 8]; 15] = [
+    [0x03, 0x21, 0x05, 0x06, 0xE8, 0x81, 0x42, 0x27],
+    [0x13, 0x41, 0x14, 0x0D, 0xD8, 0xF6, 0x23, 0x12],
+    [0x11, 0x11, 0x08, 0x08, 0xFA, 0xB2, 0x20, 0x12],
+    [0x31, 0x61, 0x0C, 0x07, 0xA8, 0x64, 0x61, 0x27],
+    [0x32, 0x21, 0x1E, 0x06, 0xE1, 0x76, 0x01, 0x28],
+    [0x02, 0x01, 0x06, 0x00, 0xA3, 0xE2, 0xF4, 0xF4],
+    [0x21, 0x61, 0x1D, 0x07, 0x82, 0x81, 0x11, 0x07],
+    [0x23, 0x21, 0x22, 0x17, 0xA2, 0x72, 0x01, 0x17],
+    [0x35, 0x11, 0x25, 0x00, 0x40, 0x73, 0x72, 0x01],
+    [0xB5, 0x01, 0x0F, 0x0F, 0xA8, 0xA5, 0x51, 0x02],
+    [0x17, 0xC1, 0x24, 0x07, 0xF8, 0xF8, 0x22, 0x12],
+    [0x71, 0x23, 0x11, 0x06, 0x65, 0x74, 0x18, 0x16],
+    [0x01, 0x02, 0xD3, 0x05, 0xC9, 0x95, 0x03, 0x02],
+    [0x61, 0x63, 0x0C, 0x00, 0x94, 0xC0, 0x33, 0xF6],
+    [0x21, 0x72, 0x0D, 0x00, 0xC1, 0xD5, 0x56, 0x06],
+];
+
+// the settings of one operator taken from the 8 instrument bytes
+struct Patch {
+    tremolo: bool,
+    vibrato: bool,
+    sustained: bool,
+    key_scale_rate: bool,
+    multiplier: u32,
+    key_scale_level: usize,
+    total_level: f32,
+    half_wave: bool,
+    attack: u8,
+    decay: u8,
+    sustain_level: f32,
+    release: u8,
+}
+
+impl Patch {
+    fn new(instrument: &[u8; 8], carrier: bool, volume: u8) -> Patch {
+        let index = carrier as usize;
+        let flags = instrument[index];
+        Patch {
+            tremolo: flags & 0x80 != 0,
+            vibrato: flags & 0x40 != 0,
+            sustained: flags & 0x20 != 0,
+            key_scale_rate: flags & 0x10 != 0,
+            multiplier: MULTIPLIERS[(flags & 0x0F) as usize],
+            key_scale_level: (instrument[2 + index] >> 6) as usize,
+            // the modulator has a total level in 0.75 dB steps, the carrier the channel volume in 3 dB steps
+            total_level: match carrier {
+                true  => volume as f32 * 3.0,
+                false => (instrument[2] & 0x3F) as f32 * 0.75,
+            },
+            half_wave: instrument[3] & (0x08 << index) != 0,
+            attack: instrument[4 + index] >> 4,
+            decay: instrument[4 + index] & 0x0F,
+            sustain_level: (instrument[6 + index] >> 4) as f32 * 3.0,
+            release: instrument[6 + index] & 0x0F,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum EnvelopeState {
+    Attack,
+    Decay,
+    Sustain,
+    Release,
+}
+
+#[derive(Clone, Copy)]
+struct Operator {
+    phase: u32,
+    state: EnvelopeState,
+    attenuation: f32, // dB
+}
+
+impl Operator {
+    fn new() -> Operator {
+        Operator {
+            phase: 0,
+            state: EnvelopeState::Release,
+            attenuation: MAX_ATTENUATION,
+        }
+    }
+
+    fn key_on(&mut self) {
+        self.phase = 0;
+        self.state = EnvelopeState::Attack;
+    }
+
+    fn key_off(&mut self) {
+        self.state = EnvelopeState::Release;
+    }
+
+    fn update_envelope(&mut self, patch: &Patch, key_scale: u8, channel_sustain: bool) {
+        match self.state {
+            EnvelopeState::Attack => {
+                if patch.attack == 15 {
+                    self.attenuation = 0.0;
+                } else {
+                    // the attack is exponential and about 6 times faster than a decay of the same rate
+                    let step = decay_step(patch.attack, key_scale) * 6.0;
+                    self.attenuation -= step * (1.0 + self.attenuation / 8.0);
+                }
+                if self.attenuation <= 0.0 {
+                    self.attenuation = 0.0;
+                    self.state = EnvelopeState::Decay;
+                }
+            },
+            EnvelopeState::Decay => {
+                self.attenuation += decay_step(patch.decay, key_scale);
+                if self.attenuation >= patch.sustain_level {
+                    self.attenuation = patch.sustain_level;
+                    self.state = EnvelopeState::Sustain;
+                }
+            },
+            EnvelopeState::Sustain => {
+                // percussive tones keep decaying with the release rate
+                if !patch.sustained {
+                    self.attenuation += decay_step(patch.release, key_scale);
+                }
+            },
+            EnvelopeState::Release => {
+                let rate = match (channel_sustain, patch.sustained) {
+                    (true, _) => 5,
+                    (false, true) => patch.release,
+                    (false, false) => 7,
+                };
+                self.attenuation += decay_step(rate, key_scale);
+            },
+        }
+        self.attenuation = self.attenuation.min(MAX_ATTENUATION);
+    }
+
+    fn output(&self, patch: &Patch, level: f32, modulation: f32) -> f32 {
+        let attenuation = self.attenuation + patch.total_level + level;
+        if attenuation >= MAX_ATTENUATION {
+            return 0.0;
+        }
+        let angle = self.phase as f32 / (1 << PHASE_BITS) as f32 * 2.0 * PI + modulation;
+        let sine = angle.sin();
+        if patch.half_wave && sine < 0.0 {
+            return 0.0;
+        }
+        sine * 10f32.powf(-attenuation / 20.0)
+    }
+}
+
+// attenuation change (dB) per sample of a decay or release rate, 96 dB take 20 s / 2^(rate / 4)
+fn decay_step(rate: u8, key_scale: u8) -> f32 {
+    if rate == 0 {
+        return 0.0;
+    }
+    let effective = (rate as u32 * 4 + key_scale as u32).min(63);
+    let seconds = 20.0 / 2f32.powf(effective as f32 / 4.0);
+    96.0 / (seconds * SAMPLE_RATE)
+}
+
+#[derive(Clone, Copy)]
+struct Channel {
+    fnum: u32,
+    block: u32,
+    sustain: bool,
+    key_on: bool,
+    instrument: u8,
+    volume: u8,
+    modulator: Operator,
+    carrier: Operator,
+    // the last two modulator outputs for the feedback
+    feedback: [f32; 2],
+}
+
+impl Channel {
+    fn new() -> Channel {
+        Channel {
+            fnum: 0,
+            block: 0,
+            sustain: false,
+            key_on: false,
+            instrument: 0,
+            volume: 0,
+            modulator: Operator::new(),
+            carrier: Operator::new(),
+            feedback: [0.0; 2],
+        }
+    }
+
+    fn set_key(&mut self, key_on: bool) {
+        if key_on && !self.key_on {
+            self.modulator.key_on();
+            self.carrier.key_on();
+        } else if !key_on && self.key_on {
+            self.modulator.key_off();
+            self.carrier.key_off();
+        }
+        self.key_on = key_on;
+    }
+
+    fn phase_step(&self, patch: &Patch, vibrato: i32) -> u32 {
+        let fnum = match patch.vibrato {
+            true  => (self.fnum as i32 + ((self.fnum as i32 * vibrato) >> 8)) as u32,
+            false => self.fnum,
+        };
+        ((fnum << self.block) * patch.multiplier) >> 2
+    }
+
+    fn key_scale_level(&self, patch: &Patch) -> f32 {
+        let level = KEY_SCALE_LEVELS[(self.fnum >> 5) as usize] - 6.0 * (7 - self.block) as f32;
+        let factor = [0.0, 0.25, 0.5, 1.0][patch.key_scale_level];
+        level.max(0.0) * factor
+    }
+
+    fn sample(&mut self, instrument: &[u8; 8], tremolo: f32, vibrato: i32) -> f32 {
+        let modulator = Patch::new(instrument, false, self.volume);
+        let carrier = Patch::new(instrument, true, self.volume);
+
+        let key_code = ((self.block << 1) | (self.fnum >> 8)) as u8;
+        for (operator, patch) in [(&mut self.modulator, &modulator), (&mut self.carrier, &carrier)].iter_mut() {
+            let key_scale = match patch.key_scale_rate {
+                true  => key_code,
+                false => key_code >> 2,
+            };
+            operator.update_envelope(patch, key_scale, self.sustain);
+        }
+
+        let feedback = instrument[3] & 0x07;
+        let feedback = match feedback {
+            0 => 0.0,
+            _ => (self.feedback[0] + self.feedback[1]) / 2.0 * PI * 2f32.powi(feedback as i32 - 5),
+        };
+        let level = |patch: &Patch| {
+            let tremolo = if patch.tremolo { tremolo } else { 0.0 };
+            self.key_scale_level(patch) + tremolo
+        };
+        let modulation = self.modulator.output(&modulator, level(&modulator), feedback);
+        let output = self.carrier.output(&carrier, level(&carrier), modulation * MODULATION_DEPTH);
+        self.feedback = [self.feedback[1], modulation];
+
+        self.modulator.phase = (self.modulator.phase + self.phase_step(&modulator, vibrato)) & PHASE_MASK;
+        self.carrier.phase = (self.carrier.phase + self.phase_step(&carrier, vibrato)) & PHASE_MASK;
+        output
+    }
+}
+
+pub struct Vrc7Audio {
+    address: u8,
+    custom: [u8; 8],
+    channels: [Channel; CHANNELS],
+    silenced: bool,
+    counter: u32,
+    am_counter: u32,
+    pm_counter: u32,
+    output: f32,
+}
+
+impl Vrc7Audio {
+    pub fn new() -> Vrc7Audio {
+        Vrc7Audio {
+            address: 0,
+            custom: [0; 8],
+            channels: [Channel::new(); CHANNELS],
+            silenced: false,
+            counter: 0,
+            am_counter: 0,
+            pm_counter: 0,
+            output: 0.0,
+        }
+    }
+
+    // 0x9010
+    pub fn write_address(&mut self, data: u8) {
+        self.address = data;
+    }
+
+    // 0x9030
+    pub fn write_data(&mut self, data: u8) {
+        let channel = (self.address & 0x0F) as usize;
+        match self.address {
+            0x00..=0x07 => self.custom[self.address as usize] = data,
+            0x10..=0x15 => {
+                let channel = &mut self.channels[channel];
+                channel.fnum = (channel.fnum & 0x100) | data as u32;
+            },
+            0x20..=0x25 => { // --SK BBBF
+                let channel = &mut self.channels[channel];
+                channel.fnum = (channel.fnum & 0xFF) | ((data & 0x01) as u32) << 8;
+                channel.block = ((data >> 1) & 0x07) as u32;
+                channel.sustain = data & 0x20 != 0;
+                channel.set_key(data & 0x10 != 0);
+            },
+            0x30..=0x35 => { // IIII VVVV
+                let channel = &mut self.channels[channel];
+                channel.instrument = data >> 4;
+                channel.volume = data & 0x0F;
+            },
+            _ => (),
+        }
+    }
+
+    // bit 7 of 0xE000 silences and resets the sound
+    pub fn set_silenced(&mut self, silenced: bool) {
+        if silenced && !self.silenced {
+            self.channels = [Channel::new(); CHANNELS];
+            self.output = 0.0;
+        }
+        self.silenced = silenced;
+    }
+
+    pub fn clock(&mut self) { // every cpu cycle
+        self.counter += 1;
+        if self.counter < SAMPLE_PERIOD {
+            return;
+        }
+        self.counter = 0;
+        if self.silenced {
+            return;
+        }
+
+        self.am_counter = (self.am_counter + 1) % AM_PERIOD;
+        self.pm_counter = self.pm_counter.wrapping_add(1);
+        let triangle = self.am_counter as f32 / AM_PERIOD as f32 * 2.0;
+        let tremolo = AM_DEPTH * (1.0 - (triangle - 1.0).abs());
+        let vibrato = PM_STEPS[((self.pm_counter >> 10) & 0x07) as usize];
+
+        let custom = self.custom;
+        let mut output = 0.0;
+        for channel in self.channels.iter_mut() {
+            let instrument = match channel.instrument {
+                0 => &custom,
+                n => &INSTRUMENTS[n as usize - 1],
+            };
+            output += channel.sample(instrument, tremolo, vibrato);
+        }
+        self.output = output * CHANNEL_SCALE;
+    }
+
+    pub fn output(&self) -> f32 {
+        self.output
+    }
+}
@@ -0,0 +1,86 @@
+// irq counter of the Konami VRC4, VRC6 and VRC7: an 8 bit counter counting up to 0xFF, clocked
+// every cpu cycle or, in the scanline mode, by a prescaler every 113.667 cpu cycles (341 / 3)
+
+const PRESCALER_PERIOD: i16 = 341;
+
+pub struct VrcIrq {
+    latch: u8,
+    counter: u8,
+    prescaler: i16,
+    enable: bool,
+    enable_after_ack: bool,
+    cycle_mode: bool,
+    pending: bool,
+}
+
+impl VrcIrq {
+    pub fn new() -> VrcIrq {
+        VrcIrq {
+            latch: 0,
+            counter: 0,
+            prescaler: PRESCALER_PERIOD,
+            enable: false,
+            enable_after_ack: false,
+            cycle_mode: false,
+            pending: false,
+        }
+    }
+
+    pub fn set_latch(&mut self, data: u8) {
+        self.latch = data;
+    }
+
+    // VRC4 writes the latch by nibbles
+    pub fn set_latch_low(&mut self, data: u8) {
+        self.latch = (self.latch & 0xF0) | (data & 0x0F);
+    }
+
+    pub fn set_latch_high(&mut self, data: u8) {
+        self.latch = (self.latch & 0x0F) | (data & 0x0F) << 4;
+    }
+
+    // ---- -MEA
+    pub fn write_control(&mut self, data: u8) {
+        self.enable_after_ack = data & 0x01 != 0;
+        self.enable = data & 0x02 != 0;
+        self.cycle_mode = data & 0x04 != 0;
+        self.pending = false;
+        if self.enable {
+            self.counter = self.latch;
+            self.prescaler = PRESCALER_PERIOD;
+        }
+    }
+
+    pub fn acknowledge(&mut self) {
+        self.pending = false;
+        self.enable = self.enable_after_ack;
+    }
+
+    pub fn pending(&self) -> bool {
+        self.pending
+    }
+
+    pub fn clock(&mut self) { // every cpu cycle
+        if !self.enable {
+            return;
+        }
+        if self.cycle_mode {
+            self.clock_counter();
+            return;
+        }
+        self.prescaler -= 3;
+        if self.prescaler <= 0 {
+            self.prescaler += PRESCALER_PERIOD;
+            self.clock_counter();
+        }
+    }
+
+    fn clock_counter(&mut self) {
+        if self.counter == 0xFF {
+            self.counter = self.latch;
+            self.pending = true;
+        } else {
+            self.counter += 1;
+        }
+    }
+}
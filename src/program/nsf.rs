@@ -0,0 +1,112 @@
+use super::CartridgeError;
+
+pub const SIGNATURE: &[u8] = b"NESM\x1A";
+const HEADER_SIZE: usize = 0x80;
+
+// expansion sound chips
+pub const VRC6: u8 = 0x01;
+pub const VRC7: u8 = 0x02;
+pub const FDS: u8 = 0x04;
+pub const MMC5: u8 = 0x08;
+pub const N163: u8 = 0x10;
+pub const SUNSOFT_5B: u8 = 0x20;
+const CHIP_NAMES: [(u8, &str); 6] = [
+    (VRC6, "VRC6"), (VRC7, "VRC7"), (FDS, "FDS"), (MMC5, "MMC5"), (N163, "N163"), (SUNSOFT_5B, "5B"),
+];
+
+// default play rate of ntsc tunes, in microseconds
+const NTSC_SPEED: u16 = 16639;
+const PAL_SPEED: u16 = 19997;
+
+#[derive(Debug, Clone)]
+pub struct NsfHeader {
+    pub version: u8,
+    pub songs: usize,
+    pub starting_song: usize, // from 0
+    pub load_address: u16,
+    pub init_address: u16,
+    pub play_address: u16,
+    pub name: String,
+    pub artist: String,
+    pub copyright: String,
+    pub ntsc_speed: u16,
+    pub bankswitch: [u8; 8],
+    pub pal_speed: u16,
+    pub pal: bool,
+    pub expansion: u8,
+}
+
+impl NsfHeader {
+    pub fn bankswitched(&self) -> bool {
+        self.bankswitch.iter().any(|bank| *bank != 0)
+    }
+
+    // the console is always ntsc, pal-only tunes keep their own rate
+    pub fn play_speed(&self) -> u16 {
+        match (self.pal, self.ntsc_speed, self.pal_speed) {
+            (true, _, 0) => PAL_SPEED,
+            (true, _, speed) => speed,
+            (false, 0, _) => NTSC_SPEED,
+            (false, speed, _) => speed,
+        }
+    }
+
+    pub fn has_expansion(&self, chip: u8) -> bool {
+        self.expansion & chip != 0
+    }
+
+    pub fn expansion_names(&self) -> Vec<&'static str> {
+        CHIP_NAMES.iter()
+            .filter(|(chip, _)| self.has_expansion(*chip))
+            .map(|(_, name)| *name)
+            .collect()
+    }
+}
+
+pub fn is_nsf(data: &[u8]) -> bool {
+    data.starts_with(SIGNATURE)
+}
+
+// header and the program data following it
+pub fn parse(data: &[u8]) -> Result<(NsfHeader, Vec<u8>), CartridgeError> {
+    if !is_nsf(data) {
+        return Err(CartridgeError::BadMagic);
+    }
+    if data.len() <= HEADER_SIZE {
+        return Err(CartridgeError::Truncated { expected: HEADER_SIZE + 1, actual: data.len() });
+    }
+    let word = |offset: usize| data[offset] as u16 | (data[offset + 1] as u16) << 8;
+    let text = |offset: usize| {
+        let field = &data[offset..offset + 32];
+        let end = field.iter().position(|byte| *byte == 0).unwrap_or(field.len());
+        String::from_utf8_lossy(&field[..end]).trim().to_string()
+    };
+    let mut bankswitch = [0; 8];
+    bankswitch.copy_from_slice(&data[0x70..0x78]);
+    let header = NsfHeader {
+        version: data[0x05],
+        songs: (data[0x06] as usize).max(1),
+        starting_song: (data[0x07] as usize).max(1) - 1,
+        load_address: word(0x08),
+        init_address: word(0x0A),
+        play_address: word(0x0C),
+        name: text(0x0E),
+        artist: text(0x2E),
+        copyright: text(0x4E),
+        ntsc_speed: word(0x6E),
+        bankswitch,
+        pal_speed: word(0x78),
+        pal: data[0x7A] & 0x03 == 0x01,
+        expansion: data[0x7B],
+    };
+
+    // nsf2 keeps metadata after the program, its length is in the header
+    let program_length = data[0x7D] as usize | (data[0x7E] as usize) << 8 | (data[0x7F] as usize) << 16;
+    let program = &data[HEADER_SIZE..];
+    let program = match header.version >= 2 && program_length != 0 {
+        true  => &program[..program_length.min(program.len())],
+        false => program,
+    };
+    info!("{:?}", header);
+    Ok((header, program.to_vec()))
+}
@@ -0,0 +1,405 @@
+use std::convert::TryFrom;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use super::checksum::crc32;
+
+// patch files with these extensions next to a rom are applied automatically
+const PATCH_EXTENSIONS: [&str; 3] = ["ips", "bps", "ups"];
+// target sizes of bps and ups come from the patch, larger ones are rejected before allocating
+const MAX_TARGET_SIZE: usize = 64 * 1024 * 1024;
+// a usize takes at most 10 bytes of 7 bits
+const MAX_NUMBER_BYTES: usize = 10;
+
+#[derive(Debug)]
+pub enum PatchError {
+    UnknownFormat,
+    Malformed,
+    PatchChecksum,
+    SourceChecksum,
+    TargetChecksum,
+}
+
+impl fmt::Display for PatchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PatchError::UnknownFormat => write!(f, "unknown patch format"),
+            PatchError::Malformed => write!(f, "patch data is malformed"),
+            PatchError::PatchChecksum => write!(f, "patch file is corrupted (checksum mismatch)"),
+            PatchError::SourceChecksum => write!(f, "patch is made for another rom (checksum mismatch)"),
+            PatchError::TargetChecksum => write!(f, "patched rom checksum mismatch"),
+        }
+    }
+}
+
+impl std::error::Error for PatchError {}
+
+pub fn find_patch(rom_path: &Path) -> Option<PathBuf> {
+    PATCH_EXTENSIONS.iter()
+        .map(|extension| rom_path.with_extension(extension))
+        .find(|patch_path| patch_path.is_file())
+}
+
+// format is detected by the signature of the patch
+pub fn apply_patch(rom: &[u8], patch: &[u8]) -> Result<Vec<u8>, PatchError> {
+    if patch.starts_with(b"PATCH") {
+        apply_ips(rom, patch)
+    } else if patch.starts_with(b"BPS1") {
+        apply_bps(rom, patch)
+    } else if patch.starts_with(b"UPS1") {
+        apply_ups(rom, patch)
+    } else {
+        Err(PatchError::UnknownFormat)
+    }
+}
+
+struct PatchReader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> PatchReader<'a> {
+    fn new(data: &'a [u8], position: usize) -> Self {
+        PatchReader { data, position }
+    }
+
+    fn read_bytes(&mut self, amount: usize) -> Result<&'a [u8], PatchError> {
+        let end = self.position.checked_add(amount).ok_or(PatchError::Malformed)?;
+        let bytes = self.data.get(self.position..end).ok_or(PatchError::Malformed)?;
+        self.position = end;
+        Ok(bytes)
+    }
+
+    fn read_byte(&mut self) -> Result<u8, PatchError> {
+        Ok(self.read_bytes(1)?[0])
+    }
+
+    // big-endian number, used by ips
+    fn read_be(&mut self, size: usize) -> Result<usize, PatchError> {
+        Ok(self.read_bytes(size)?.iter().fold(0, |value, byte| value << 8 | *byte as usize))
+    }
+
+    // variable-length number of bps and ups, every byte has 7 bits of data, the last one has bit 7 set
+    fn read_number(&mut self) -> Result<usize, PatchError> {
+        let mut value: usize = 0;
+        let mut shift: usize = 1;
+        for _ in 0..MAX_NUMBER_BYTES {
+            let byte = self.read_byte()?;
+            let bits = ((byte & 0x7F) as usize).checked_mul(shift).ok_or(PatchError::Malformed)?;
+            value = value.checked_add(bits).ok_or(PatchError::Malformed)?;
+            if byte & 0x80 != 0 {
+                return Ok(value);
+            }
+            shift = shift.checked_mul(0x80).ok_or(PatchError::Malformed)?;
+            value = value.checked_add(shift).ok_or(PatchError::Malformed)?;
+        }
+        Err(PatchError::Malformed)
+    }
+}
+
+// records for every changed range of the data, the data can't be longer than 16mb
+pub fn create_ips(original: &[u8], modified: &[u8]) -> Vec<u8> {
+    const MAX_RECORD_SIZE: usize = 0xFFFF;
+    let mut patch = b"PATCH".to_vec();
+    let changed = |idx: usize| original.get(idx) != modified.get(idx);
+    let mut idx = 0;
+    while idx < modified.len() {
+        if !changed(idx) {
+            idx += 1;
+            continue;
+        }
+        // the offset 0x454F46 would be read as "EOF"
+        let start = match idx {
+            0x454F46 => idx - 1,
+            _ => idx,
+        };
+        let mut end = idx;
+        while end < modified.len() && end - start < MAX_RECORD_SIZE && changed(end) {
+            end += 1;
+        }
+        patch.extend_from_slice(&(start as u32).to_be_bytes()[1..]);
+        patch.extend_from_slice(&((end - start) as u16).to_be_bytes());
+        patch.extend_from_slice(&modified[start..end]);
+        idx = end;
+    }
+    patch.extend_from_slice(b"EOF");
+    if modified.len() < original.len() {
+        patch.extend_from_slice(&(modified.len() as u32).to_be_bytes()[1..]);
+    }
+    patch
+}
+
+// "PATCH", records of [offset: 3, size: 2, data] or [offset: 3, 0, count: 2, value: 1] for run-length,
+// "EOF" and an optional size to truncate the rom
+fn apply_ips(rom: &[u8], patch: &[u8]) -> Result<Vec<u8>, PatchError> {
+    let mut output = rom.to_vec();
+    let mut reader = PatchReader::new(patch, 5);
+    loop {
+        let offset = reader.read_be(3)?;
+        if offset == 0x454F46 { // "EOF"
+            break;
+        }
+        let data = match reader.read_be(2)? {
+            0 => {
+                let count = reader.read_be(2)?;
+                vec![reader.read_byte()?; count]
+            },
+            size => reader.read_bytes(size)?.to_vec(),
+        };
+        if output.len() < offset + data.len() {
+            output.resize(offset + data.len(), 0);
+        }
+        output[offset..offset + data.len()].copy_from_slice(&data);
+    }
+    if let Ok(size) = reader.read_be(3) {
+        output.truncate(size);
+    }
+    Ok(output)
+}
+
+fn read_target_size(reader: &mut PatchReader) -> Result<usize, PatchError> {
+    let size = reader.read_number()?;
+    if size > MAX_TARGET_SIZE {
+        return Err(PatchError::Malformed);
+    }
+    Ok(size)
+}
+
+// bps and ups end with crc32 of the source, the target and the patch itself
+fn check_footer(rom: &[u8], patch: &[u8]) -> Result<(u32, u32), PatchError> {
+    if patch.len() < 16 {
+        return Err(PatchError::Malformed);
+    }
+    let footer = &patch[patch.len() - 12..];
+    let read_u32 = |bytes: &[u8]| u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+    if crc32(&patch[..patch.len() - 4]) != read_u32(&footer[8..]) {
+        return Err(PatchError::PatchChecksum);
+    }
+    if crc32(rom) != read_u32(&footer[..4]) {
+        return Err(PatchError::SourceChecksum);
+    }
+    Ok((read_u32(&footer[..4]), read_u32(&footer[4..8])))
+}
+
+fn apply_bps(rom: &[u8], patch: &[u8]) -> Result<Vec<u8>, PatchError> {
+    let (_, target_crc) = check_footer(rom, patch)?;
+    let actions_end = patch.len() - 12;
+    let mut reader = PatchReader::new(&patch[..actions_end], 4);
+    let _source_size = reader.read_number()?;
+    let target_size = read_target_size(&mut reader)?;
+    let metadata_size = reader.read_number()?;
+    reader.read_bytes(metadata_size)?;
+
+    let mut output: Vec<u8> = Vec::with_capacity(target_size);
+    let mut source_offset: isize = 0;
+    let mut target_offset: isize = 0;
+    while reader.position < actions_end {
+        let data = reader.read_number()?;
+        let length = (data >> 2) + 1;
+        if length > target_size - output.len() {
+            return Err(PatchError::Malformed);
+        }
+        match data & 0x03 {
+            0 => { // source read
+                let start = output.len();
+                let bytes = rom.get(start..start + length).ok_or(PatchError::Malformed)?;
+                output.extend_from_slice(bytes);
+            },
+            1 => { // target read
+                output.extend_from_slice(reader.read_bytes(length)?);
+            },
+            command => { // source copy / target copy, offset is relative to the previous copy
+                let data = reader.read_number()?;
+                let relative = match data & 0x01 {
+                    0 => (data >> 1) as isize,
+                    _ => -((data >> 1) as isize),
+                };
+                let copy_offset = match command {
+                    2 => &mut source_offset,
+                    _ => &mut target_offset,
+                };
+                *copy_offset += relative;
+                for _ in 0..length {
+                    let idx = usize::try_from(*copy_offset).map_err(|_| PatchError::Malformed)?;
+                    let byte = match command {
+                        2 => rom.get(idx),
+                        _ => output.get(idx),
+                    };
+                    let byte = *byte.ok_or(PatchError::Malformed)?;
+                    output.push(byte);
+                    *copy_offset += 1;
+                }
+            },
+        }
+    }
+    if output.len() != target_size || crc32(&output) != target_crc {
+        return Err(PatchError::TargetChecksum);
+    }
+    Ok(output)
+}
+
+// hunks of [relative offset, xor data terminated by 0]
+fn apply_ups(rom: &[u8], patch: &[u8]) -> Result<Vec<u8>, PatchError> {
+    let (_, target_crc) = check_footer(rom, patch)?;
+    let hunks_end = patch.len() - 12;
+    let mut reader = PatchReader::new(&patch[..hunks_end], 4);
+    let _source_size = reader.read_number()?;
+    let target_size = read_target_size(&mut reader)?;
+
+    let mut output = rom.to_vec();
+    output.resize(target_size, 0);
+    let mut position = 0;
+    while reader.position < hunks_end {
+        position = reader.read_number()?.checked_add(position).ok_or(PatchError::Malformed)?;
+        loop {
+            let byte = reader.read_byte()?;
+            if let Some(target) = output.get_mut(position) {
+                *target ^= byte;
+            }
+            position = position.saturating_add(1);
+            if byte == 0 {
+                break;
+            }
+        }
+    }
+    if crc32(&output) != target_crc {
+        return Err(PatchError::TargetChecksum);
+    }
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::CartridgeError;
+
+    // variable-length number of bps and ups
+    fn number(mut value: usize) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        loop {
+            let bits = (value & 0x7F) as u8;
+            value >>= 7;
+            if value == 0 {
+                bytes.push(0x80 | bits);
+                return bytes;
+            }
+            bytes.push(bits);
+            value -= 1;
+        }
+    }
+
+    fn with_footer(mut patch: Vec<u8>, source: &[u8], target: &[u8]) -> Vec<u8> {
+        patch.extend_from_slice(&crc32(source).to_le_bytes());
+        patch.extend_from_slice(&crc32(target).to_le_bytes());
+        let patch_crc = crc32(&patch);
+        patch.extend_from_slice(&patch_crc.to_le_bytes());
+        patch
+    }
+
+    // a single target read of the whole target
+    fn bps_patch(source: &[u8], target: &[u8]) -> Vec<u8> {
+        let mut patch = b"BPS1".to_vec();
+        patch.extend(number(source.len()));
+        patch.extend(number(target.len()));
+        patch.extend(number(0));
+        patch.extend(number((target.len() - 1) << 2 | 1));
+        patch.extend_from_slice(target);
+        with_footer(patch, source, target)
+    }
+
+    // a single hunk, every byte of the target has to differ from the source
+    fn ups_patch(source: &[u8], target: &[u8]) -> Vec<u8> {
+        let mut patch = b"UPS1".to_vec();
+        patch.extend(number(source.len()));
+        patch.extend(number(target.len()));
+        patch.extend(number(0));
+        patch.extend(source.iter().zip(target).map(|(source, target)| source ^ target));
+        patch.push(0);
+        with_footer(patch, source, target)
+    }
+
+    fn patch_error(rom: &[u8], patch: &[u8]) -> CartridgeError {
+        CartridgeError::from(apply_patch(rom, patch).unwrap_err())
+    }
+
+    const SOURCE: [u8; 4] = [1, 2, 3, 4];
+    const TARGET: [u8; 4] = [5, 6, 7, 8];
+
+    #[test]
+    fn ips_records() {
+        let mut patch = b"PATCH".to_vec();
+        patch.extend_from_slice(&[0x00, 0x00, 0x01, 0x00, 0x02, 0xAA, 0xBB]);
+        // run-length record which extends the rom
+        patch.extend_from_slice(&[0x00, 0x00, 0x06, 0x00, 0x00, 0x00, 0x03, 0xCC]);
+        patch.extend_from_slice(b"EOF");
+        let output = apply_patch(&[0; 4], &patch).unwrap();
+        assert_eq!(output, [0x00, 0xAA, 0xBB, 0x00, 0x00, 0x00, 0xCC, 0xCC, 0xCC]);
+    }
+
+    #[test]
+    fn ips_truncation() {
+        let mut patch = b"PATCHEOF".to_vec();
+        patch.extend_from_slice(&[0x00, 0x00, 0x02]);
+        assert_eq!(apply_patch(&SOURCE, &patch).unwrap(), [1, 2]);
+    }
+
+    #[test]
+    fn ips_truncated_record() {
+        let patch = b"PATCH\x00\x00\x01\x00\x04\xAA";
+        assert!(matches!(patch_error(&SOURCE, patch), CartridgeError::Patch(PatchError::Malformed)));
+    }
+
+    #[test]
+    fn bps_and_ups() {
+        assert_eq!(apply_patch(&SOURCE, &bps_patch(&SOURCE, &TARGET)).unwrap(), TARGET);
+        assert_eq!(apply_patch(&SOURCE, &ups_patch(&SOURCE, &TARGET)).unwrap(), TARGET);
+    }
+
+    #[test]
+    fn bad_checksums() {
+        for patch in [bps_patch(&SOURCE, &TARGET), ups_patch(&SOURCE, &TARGET)].iter() {
+            let mut corrupted = patch.clone();
+            corrupted[8] ^= 0xFF;
+            assert!(matches!(patch_error(&SOURCE, &corrupted), CartridgeError::Patch(PatchError::PatchChecksum)));
+            assert!(matches!(patch_error(&TARGET, patch), CartridgeError::Patch(PatchError::SourceChecksum)));
+        }
+    }
+
+    #[test]
+    fn unknown_format() {
+        assert!(matches!(patch_error(&SOURCE, b"PAT"), CartridgeError::Patch(PatchError::UnknownFormat)));
+    }
+
+    #[test]
+    fn oversized_numbers() {
+        // no terminating byte within MAX_NUMBER_BYTES
+        let patch = with_footer([&b"BPS1"[..], &[0x00; 12]].concat(), &SOURCE, &TARGET);
+        assert!(matches!(patch_error(&SOURCE, &patch), CartridgeError::Patch(PatchError::Malformed)));
+        // more than 64 bits
+        let patch = with_footer([&b"UPS1"[..], &[0x7F; 9], &[0xFF]].concat(), &SOURCE, &TARGET);
+        assert!(matches!(patch_error(&SOURCE, &patch), CartridgeError::Patch(PatchError::Malformed)));
+        // target size beyond MAX_TARGET_SIZE
+        let patch = with_footer([&b"UPS1"[..], &number(4), &number(MAX_TARGET_SIZE + 1)].concat(), &SOURCE, &TARGET);
+        assert!(matches!(patch_error(&SOURCE, &patch), CartridgeError::Patch(PatchError::Malformed)));
+    }
+
+    #[test]
+    fn ips_round_trip() {
+        let original: Vec<u8> = (0..0x460000).map(|idx| idx as u8).collect();
+        let mut modified = original.clone();
+        modified[0] = 0xFF;
+        // longer than one record
+        modified[0x1000..0x21000].iter_mut().for_each(|byte| *byte ^= 0xFF);
+        // a record at this offset would be read as "EOF"
+        modified[0x454F46] ^= 0xFF;
+        let patch = create_ips(&original, &modified);
+        assert_eq!(apply_patch(&original, &patch).unwrap(), modified);
+
+        // the patched data is truncated or extended
+        let shorter = &modified[..0x300000];
+        assert_eq!(apply_patch(&original, &create_ips(&original, shorter)).unwrap(), shorter);
+        let mut longer = modified.clone();
+        longer.extend_from_slice(&[0x01, 0x02, 0x03]);
+        assert_eq!(apply_patch(&original, &create_ips(&original, &longer)).unwrap(), longer);
+    }
+}
@@ -0,0 +1,164 @@
+use super::{CartridgeError, ConsoleType, RomHeader, RomImage, Timing};
+
+pub const SIGNATURE: &[u8] = b"UNIF";
+const HEADER_SIZE: usize = 32;
+const RAM_SIZE: usize = 8192;
+
+// boards of the supported mappers, board names are compared without the "NES-", "UNL-", ... prefix.
+// konami boards are named by their pcb number
+const BOARDS: [(&str, u16, u8); 54] = [
+    ("NROM", 0, 0), ("NROM-128", 0, 0), ("NROM-256", 0, 0), ("RROM", 0, 0), ("RROM-128", 0, 0),
+    ("SAROM", 1, 0), ("SBROM", 1, 0), ("SCROM", 1, 0), ("SEROM", 1, 0), ("SGROM", 1, 0), ("SKROM", 1, 0),
+    ("SLROM", 1, 0), ("SL1ROM", 1, 0), ("SNROM", 1, 0), ("SOROM", 1, 0), ("SUROM", 1, 0), ("SXROM", 1, 0),
+    ("UNROM", 2, 2), ("UOROM", 2, 2),
+    ("CNROM", 3, 2),
+    ("TBROM", 4, 0), ("TEROM", 4, 0), ("TFROM", 4, 0), ("TGROM", 4, 0), ("TKROM", 4, 0), ("TLROM", 4, 0),
+    ("TL1ROM", 4, 0), ("TR1ROM", 4, 0), ("TSROM", 4, 0), ("TVROM", 4, 0), ("B4", 4, 0),
+    ("EKROM", 5, 0), ("ELROM", 5, 0), ("ETROM", 5, 0), ("EWROM", 5, 0),
+    ("ANROM", 7, 1), ("AN1ROM", 7, 1), ("AMROM", 7, 2), ("AOROM", 7, 0), ("ANROM-A", 7, 1),
+    ("PNROM", 9, 0), ("PEEOROM", 9, 0),
+    ("FJROM", 10, 0), ("FKROM", 10, 0),
+    ("351618", 22, 0), ("351951", 24, 0), ("351949A", 26, 0), ("352402", 85, 1), ("353429", 85, 2), ("VRC7", 85, 0),
+    ("NAMCOT-163", 19, 0), ("JLROM", 69, 0), ("JSROM", 69, 0), ("BTR", 69, 0),
+];
+
+// boards of these mappers have the mirroring wired, the others leave it to the mapper
+const WIRED_MIRRORING: [u16; 3] = [0, 2, 3];
+
+// mapper and submapper of a board
+fn board_mapper(board: &str) -> Option<(u16, u8)> {
+    let find = |name: &str| BOARDS.iter()
+        .find(|(board_name, _, _)| board_name.eq_ignore_ascii_case(name))
+        .map(|(_, mapper, submapper)| (*mapper, *submapper));
+    find(board).or_else(|| board.find('-').and_then(|idx| find(&board[idx + 1..])))
+}
+
+fn chunk_string(data: &[u8]) -> String {
+    let end = data.iter().position(|byte| *byte == 0).unwrap_or(data.len());
+    String::from_utf8_lossy(&data[..end]).trim().to_string()
+}
+
+// "UNIF", revision and reserved bytes, then chunks of [id: 4, length: 4, data]
+pub fn parse(data: &[u8]) -> Result<RomImage, CartridgeError> {
+    if data.len() < HEADER_SIZE || !data.starts_with(SIGNATURE) {
+        return Err(CartridgeError::BadMagic);
+    }
+    let mut board = None;
+    let mut title = None;
+    let mut prg_chunks: [Option<&[u8]>; 16] = [None; 16];
+    let mut chr_chunks: [Option<&[u8]>; 16] = [None; 16];
+    let mut mirroring = None;
+    let mut battery = false;
+    let mut timing = Timing::Ntsc;
+
+    let mut idx = HEADER_SIZE;
+    while idx + 8 <= data.len() {
+        let id = &data[idx..idx + 4];
+        let length = u32::from_le_bytes([data[idx + 4], data[idx + 5], data[idx + 6], data[idx + 7]]) as usize;
+        idx += 8;
+        let expected = idx + length;
+        let chunk = data.get(idx..expected).ok_or(CartridgeError::Truncated { expected, actual: data.len() })?;
+        idx = expected;
+
+        let bank = (id[3] as char).to_digit(16).unwrap_or(0) as usize;
+        match &id[..3] {
+            b"PRG" => prg_chunks[bank] = Some(chunk),
+            b"CHR" => chr_chunks[bank] = Some(chunk),
+            _ => match id {
+                b"MAPR" => board = Some(chunk_string(chunk)),
+                b"NAME" => title = Some(chunk_string(chunk)),
+                b"MIRR" => mirroring = chunk.first().copied(),
+                b"BATR" => battery = chunk.first().map_or(true, |battery| *battery != 0),
+                b"TVCI" => timing = match chunk.first() {
+                    Some(1) => Timing::Pal,
+                    Some(2) => Timing::MultiRegion,
+                    _ => Timing::Ntsc,
+                },
+                _ => (),
+            },
+        }
+    }
+
+    let board = board.unwrap_or_default();
+    info!("unif board: {}", board);
+    let (mapper, submapper) = board_mapper(&board).ok_or_else(|| CartridgeError::UnsupportedBoard(board.clone()))?;
+    let prg_rom: Vec<u8> = prg_chunks.iter().flatten().flat_map(|chunk| chunk.iter().copied()).collect();
+    let chr_rom: Vec<u8> = chr_chunks.iter().flatten().flat_map(|chunk| chunk.iter().copied()).collect();
+    if prg_rom.is_empty() {
+        return Err(CartridgeError::NoPrgRom);
+    }
+
+    // 0 - horizontal, 1 - vertical, 2 and 3 - single screen, 4 - four screen, 5 - mapper controlled.
+    // single screen is only set by mappers here, boards with wired mirroring can't have it
+    if let Some(2..=3) = mirroring {
+        if WIRED_MIRRORING.contains(&mapper) {
+            return Err(CartridgeError::UnsupportedBoard(format!("{} with single screen mirroring", board)));
+        }
+    }
+    let header = RomHeader {
+        nes2: false,
+        mapper,
+        submapper,
+        prg_rom_size: prg_rom.len(),
+        chr_rom_size: chr_rom.len(),
+        prg_ram_size: if battery { 0 } else { RAM_SIZE },
+        prg_nvram_size: if battery { RAM_SIZE } else { 0 },
+        chr_ram_size: if chr_rom.is_empty() { RAM_SIZE } else { 0 },
+        chr_nvram_size: 0,
+        vertical_mirroring: mirroring == Some(1),
+        four_screen: mirroring == Some(4),
+        battery,
+        trainer: false,
+        console_type: ConsoleType::Nes,
+        timing,
+        misc_roms: 0,
+        expansion_device: 0,
+    };
+    Ok(RomImage { header, prg_rom, chr_rom, trainer: None, title })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::Cartridge;
+
+    fn unif(board: &str, mirroring: u8) -> Vec<u8> {
+        let mut data = SIGNATURE.to_vec();
+        data.resize(HEADER_SIZE, 0);
+        let chunks: [(&[u8], &[u8]); 3] = [(b"MAPR", board.as_bytes()), (b"MIRR", &[mirroring]), (b"PRG0", &[0xEA; 16384])];
+        for (id, chunk) in chunks.iter() {
+            data.extend_from_slice(id);
+            data.extend_from_slice(&(chunk.len() as u32).to_le_bytes());
+            data.extend_from_slice(chunk);
+        }
+        data
+    }
+
+    #[test]
+    fn boards() {
+        let image = parse(&unif("NES-NROM-128", 1)).unwrap();
+        assert_eq!(image.header.mapper, 0);
+        assert!(image.header.vertical_mirroring);
+        assert_eq!(image.header.prg_rom_size, 16384);
+        assert_eq!(image.header.chr_ram_size, RAM_SIZE);
+
+        let image = parse(&unif("NES-AMROM", 4)).unwrap();
+        assert_eq!((image.header.mapper, image.header.submapper), (7, 2));
+        assert!(image.header.four_screen);
+        assert!(matches!(parse(&unif("UNL-FOO", 0)), Err(CartridgeError::UnsupportedBoard(_))));
+    }
+
+    #[test]
+    fn boards_have_mappers() {
+        for (board, _, _) in BOARDS.iter() {
+            let image = parse(&unif(board, 5)).unwrap();
+            assert!(Cartridge::create_mapper(&image.header).is_ok(), "{}", board);
+        }
+    }
+
+    #[test]
+    fn single_screen_mirroring() {
+        assert!(matches!(parse(&unif("NES-CNROM", 2)), Err(CartridgeError::UnsupportedBoard(_))));
+        assert!(parse(&unif("NES-AOROM", 3)).is_ok());
+    }
+}